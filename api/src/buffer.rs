@@ -1,10 +1,25 @@
 use mlua::{Lua, Table};
 use mlua::prelude::LuaResult;
+use serde::Serialize;
+
+use crate::builtin_fn::{call_api, call_lua};
+use crate::option::{self, Scope};
+use crate::range::Range;
 
 /// 文件类型：文件后缀
 pub fn filetype(lua: &Lua) -> LuaResult<String> {
-    let filetype = lua.load("vim.bo.filetype").eval()?;
-    Ok(filetype)
+    option::get(lua, "filetype", Scope::Buffer(0))
+}
+
+#[derive(Serialize)]
+struct FiletypeMatch<'a> {
+    filename: &'a str,
+}
+
+/// vim.filetype.match
+/// 根据文件名检测文件类型，不需要加载文件
+pub fn filetype_match(lua: &Lua, filename: &str) -> LuaResult<Option<String>> {
+    call_lua(lua, "vim.filetype.match", (FiletypeMatch { filename },))
 }
 
 /// vim.api.nvim_buf_get_lines
@@ -46,3 +61,14 @@ pub fn set_lines(
     global.raw_remove(lines_name)?;
     Ok(())
 }
+
+/// vim.api.nvim_buf_get_text
+/// 获取 range 内的文本，按行拆分
+pub fn get_text(lua: &Lua, buffer: usize, range: Range) -> LuaResult<Vec<String>> {
+    let Range { start, end } = range;
+    call_api(
+        lua,
+        "nvim_buf_get_text",
+        (buffer, start.row, start.col, end.row, end.col, [(); 0]),
+    )
+}
@@ -1,11 +1,81 @@
-use mlua::Lua;
-use mlua::prelude::LuaResult;
+use mlua::prelude::{LuaFunction, LuaMultiValue, LuaResult, LuaTable, LuaValue};
+use mlua::{Lua, LuaSerdeExt};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::error::VimError;
 
 pub fn stdpath(lua: &Lua, what: &str) -> LuaResult<String> {
-    let cmd = format!(r#"vim.fn.stdpath("{what}")"#);
-    lua.load(cmd).eval()
+    call_fn(lua, "stdpath", (what,))
 }
 
 pub fn getcwd(lua: &Lua) -> LuaResult<String> {
-    lua.load("vim.fn.getcwd()").eval()
+    call_fn(lua, "getcwd", ())
+}
+
+/// vim.fn[name](...)
+/// 参数为元组，通过 serde 转换为 lua 参数列表，返回值反序列化为 R
+pub fn call_fn<A, R>(lua: &Lua, name: &str, args: A) -> LuaResult<R>
+where
+    A: Serialize,
+    R: DeserializeOwned,
+{
+    call(lua, "fn", name, args)
+}
+
+/// vim.api[name](...)
+/// 参数为元组，通过 serde 转换为 lua 参数列表，返回值反序列化为 R
+pub fn call_api<A, R>(lua: &Lua, name: &str, args: A) -> LuaResult<R>
+where
+    A: Serialize,
+    R: DeserializeOwned,
+{
+    call(lua, "api", name, args)
+}
+
+/// 调用 lua 函数，path 为全局路径，例如 `vim.diagnostic.set`
+/// 参数为元组，通过 serde 转换为 lua 参数列表，返回值反序列化为 R
+pub fn call_lua<A, R>(lua: &Lua, path: &str, args: A) -> LuaResult<R>
+where
+    A: Serialize,
+    R: DeserializeOwned,
+{
+    let mut parts = path.split('.');
+    let mut table: LuaTable = lua.globals();
+    let mut name = parts.next().unwrap_or_default();
+    for part in parts {
+        table = table.get(name)?;
+        name = part;
+    }
+    let func: LuaFunction = table.get(name)?;
+    let result: LuaValue = func
+        .call(to_args(lua, args)?)
+        .map_err(|err| VimError::from_lua(path, &err))?;
+    lua.from_value(result)
+}
+
+fn call<A, R>(lua: &Lua, namespace: &str, name: &str, args: A) -> LuaResult<R>
+where
+    A: Serialize,
+    R: DeserializeOwned,
+{
+    let vim: LuaTable = lua.globals().get("vim")?;
+    let functions: LuaTable = vim.get(namespace)?;
+    let func: LuaFunction = functions.get(name)?;
+    let result: LuaValue = func
+        .call(to_args(lua, args)?)
+        .map_err(|err| VimError::from_lua(name, &err))?;
+    lua.from_value(result)
+}
+
+/// 元组/数组展开为多个参数，`()` 表示无参数，顶层的 None 转为 nil
+fn to_args<A: Serialize>(lua: &Lua, args: A) -> LuaResult<LuaMultiValue> {
+    match lua.to_value(&args)? {
+        LuaValue::Table(table) => table
+            .sequence_values::<LuaValue>()
+            .map(|x| x.map(|x| if x.is_null() { LuaValue::Nil } else { x }))
+            .collect(),
+        value if value.is_null() => Ok(LuaMultiValue::new()),
+        value => Ok(LuaMultiValue::from_iter([value])),
+    }
 }
@@ -0,0 +1,218 @@
+use mlua::prelude::{LuaFunction, LuaResult};
+use mlua::{Lua, LuaSerdeExt};
+use serde::Serialize;
+
+use crate::builtin_fn::call_api;
+use crate::error::VimError;
+
+/// 在窗口中执行 nvim_cmd
+const WIN_CALL: &str = r#"
+local win, cmd = ...
+vim.api.nvim_win_call(win, function()
+  vim.api.nvim_cmd(cmd, {})
+end)
+"#;
+
+/// 分割窗口的位置
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Split {
+    AboveLeft,
+    BelowRight,
+    TopLeft,
+    BotRight,
+}
+
+/// 命令修饰符，例如 `:silent` / `:keepjumps`
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Mods {
+    pub silent: bool,
+    pub emsg_silent: bool,
+    pub unsilent: bool,
+    pub noautocmd: bool,
+    pub keepalt: bool,
+    pub keepjumps: bool,
+    pub keepmarks: bool,
+    pub keeppatterns: bool,
+    pub lockmarks: bool,
+    pub vertical: bool,
+    pub horizontal: bool,
+    /// `:{tab}tab`，0 表示在当前标签页之前
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tab: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split: Option<Split>,
+}
+
+impl Mods {
+    fn is_default(&self) -> bool {
+        *self == Mods::default()
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+struct Magic {
+    file: bool,
+    bar: bool,
+}
+
+/// 结构化的 Ex 命令，通过 nvim_cmd 执行
+/// 参数不会被重新解析，默认不展开文件名中的 `%` / `#` / 通配符，路径可以包含空格等特殊字符
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Command {
+    cmd: String,
+    args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bang: Option<bool>,
+    /// 行号从 1 开始
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    range: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reg: Option<String>,
+    #[serde(skip_serializing_if = "Mods::is_default")]
+    mods: Mods,
+    magic: Magic,
+}
+
+impl Command {
+    pub fn new(cmd: &str) -> Command {
+        Command {
+            cmd: cmd.to_string(),
+            args: Vec::new(),
+            bang: None,
+            range: Vec::new(),
+            count: None,
+            reg: None,
+            mods: Mods::default(),
+            magic: Magic {
+                file: false,
+                bar: false,
+            },
+        }
+    }
+
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args
+            .extend(args.into_iter().map(|x| x.as_ref().to_string()));
+        self
+    }
+
+    pub fn bang(mut self) -> Self {
+        self.bang = Some(true);
+        self
+    }
+
+    /// 单行，行从 0 开始
+    pub fn line(mut self, row: usize) -> Self {
+        self.range = vec![row + 1];
+        self
+    }
+
+    /// 行范围 [start_row, end_row)，行从 0 开始
+    pub fn lines(mut self, start_row: usize, end_row: usize) -> Self {
+        self.range = vec![start_row + 1, end_row.max(start_row + 1)];
+        self
+    }
+
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    pub fn register(mut self, name: char) -> Self {
+        self.reg = Some(name.to_string());
+        self
+    }
+
+    pub fn mods(mut self, mods: Mods) -> Self {
+        self.mods = mods;
+        self
+    }
+
+    pub fn silent(mut self) -> Self {
+        self.mods.silent = true;
+        self
+    }
+
+    /// `:silent!`，同时忽略错误
+    pub fn silent_errors(mut self) -> Self {
+        self.mods.silent = true;
+        self.mods.emsg_silent = true;
+        self
+    }
+
+    /// 展开参数中的 `%` / `#` / 通配符，此时特殊字符需要通过 fnameescape 转义
+    pub fn expand_files(mut self, expand: bool) -> Self {
+        self.magic.file = expand;
+        self
+    }
+
+    /// vim.api.nvim_cmd
+    pub fn exec(&self, lua: &Lua) -> LuaResult<()> {
+        self.call(lua, false)?;
+        Ok(())
+    }
+
+    /// 在窗口中执行，window 为 0 时表示当前窗口
+    pub fn exec_in(&self, lua: &Lua, window: usize) -> LuaResult<()> {
+        let func: LuaFunction = lua.load(WIN_CALL).into_function()?;
+        func.call::<()>((window, lua.to_value(self)?))
+            .map_err(|err| VimError::from_lua("nvim_cmd", &err).into())
+    }
+
+    /// 执行并返回命令输出
+    pub fn output(&self, lua: &Lua) -> LuaResult<String> {
+        self.call(lua, true)
+    }
+
+    fn call(&self, lua: &Lua, output: bool) -> LuaResult<String> {
+        #[derive(Serialize)]
+        struct Opts {
+            output: bool,
+        }
+        call_api(lua, "nvim_cmd", (self, Opts { output }))
+    }
+}
+
+/// vim.fn.fnameescape
+pub fn fnameescape(lua: &Lua, path: &str) -> LuaResult<String> {
+    crate::builtin_fn::call_fn(lua, "fnameescape", (path,))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_serialize_works() {
+        let command = Command::new("mksession").bang().arg("/tmp/a b%.vim");
+        let value = serde_json::to_value(&command).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "cmd": "mksession",
+                "args": ["/tmp/a b%.vim"],
+                "bang": true,
+                "magic": { "file": false, "bar": false },
+            })
+        );
+        let command = Command::new("fold").lines(2, 5).silent();
+        let value = serde_json::to_value(&command).unwrap();
+        assert_eq!(value["range"], serde_json::json!([3, 5]));
+        assert_eq!(value["mods"]["silent"], serde_json::json!(true));
+        let command = Command::new("foldopen").silent_errors();
+        let value = serde_json::to_value(&command).unwrap();
+        assert_eq!(value["mods"]["emsg_silent"], serde_json::json!(true));
+    }
+}
@@ -0,0 +1,145 @@
+use mlua::Lua;
+use mlua::prelude::LuaResult;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::builtin_fn::{call_api, call_lua};
+use crate::quickfix::{ItemType, QuickfixItem};
+use crate::range::{Point, Range};
+
+/// vim.diagnostic.severity
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    #[default]
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Hint = 4,
+}
+
+impl Serialize for Severity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+impl<'de> Deserialize<'de> for Severity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match u8::deserialize(deserializer)? {
+            1 => Severity::Error,
+            2 => Severity::Warn,
+            3 => Severity::Info,
+            _ => Severity::Hint,
+        })
+    }
+}
+
+impl From<ItemType> for Severity {
+    fn from(value: ItemType) -> Self {
+        match value {
+            ItemType::Error => Severity::Error,
+            ItemType::Warning => Severity::Warn,
+            ItemType::Info | ItemType::None => Severity::Info,
+            ItemType::Note | ItemType::Hint => Severity::Hint,
+        }
+    }
+}
+
+/// vim.Diagnostic，行列从 0 开始
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Diagnostic {
+    pub lnum: usize,
+    #[serde(default)]
+    pub col: usize,
+    #[serde(default)]
+    pub end_lnum: usize,
+    #[serde(default)]
+    pub end_col: usize,
+    #[serde(default)]
+    pub severity: Severity,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "string_or_number"
+    )]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_data: Option<Value>,
+}
+
+impl Diagnostic {
+    pub fn new(range: Range, severity: Severity, message: &str) -> Diagnostic {
+        Diagnostic {
+            lnum: range.start.row,
+            col: range.start.col,
+            end_lnum: range.end.row,
+            end_col: range.end.col,
+            severity,
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    pub fn range(&self) -> Range {
+        Range::new(
+            Point::new(self.lnum, self.col),
+            Point::new(self.end_lnum, self.end_col),
+        )
+    }
+}
+
+impl From<&QuickfixItem> for Diagnostic {
+    fn from(item: &QuickfixItem) -> Self {
+        Diagnostic::new(Range::from(item), item.r#type.into(), item.text.as_str())
+    }
+}
+
+/// vim.api.nvim_create_namespace
+pub fn namespace(lua: &Lua, name: &str) -> LuaResult<u32> {
+    call_api(lua, "nvim_create_namespace", (name,))
+}
+
+/// vim.diagnostic.set
+/// 替换 namespace 在 buffer 中的全部诊断
+pub fn set(lua: &Lua, namespace: u32, buffer: usize, diagnostics: &[Diagnostic]) -> LuaResult<()> {
+    call_lua(lua, "vim.diagnostic.set", (namespace, buffer, diagnostics))
+}
+
+/// vim.diagnostic.reset
+/// namespace / buffer 为 None 时表示全部
+pub fn reset(lua: &Lua, namespace: Option<u32>, buffer: Option<usize>) -> LuaResult<()> {
+    call_lua(lua, "vim.diagnostic.reset", (namespace, buffer))
+}
+
+/// vim.diagnostic.get
+pub fn get(lua: &Lua, namespace: u32, buffer: Option<usize>) -> LuaResult<Vec<Diagnostic>> {
+    #[derive(Serialize)]
+    struct Opts {
+        namespace: u32,
+    }
+    call_lua(lua, "vim.diagnostic.get", (buffer, Opts { namespace }))
+}
+
+/// 诊断代码可能为数字
+fn string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(value)) => Some(value),
+        Some(Value::Null) | None => None,
+        Some(value) => Some(value.to_string()),
+    })
+}
@@ -0,0 +1,102 @@
+use std::fmt::{Display, Formatter};
+
+use mlua::Error;
+
+/// vimscript / nvim api 调用抛出的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VimError {
+    /// 调用的函数名
+    pub function: String,
+    /// vim 错误码，例如 `E117` 对应 117
+    pub code: Option<u32>,
+    /// 错误信息(不含错误码)
+    pub message: String,
+}
+
+impl VimError {
+    /// 从 lua 调用错误构建
+    pub fn from_lua(function: &str, err: &Error) -> VimError {
+        Self::parse(function, lua_error_message(err).as_str())
+    }
+
+    /// 解析 `Vim:E117: Unknown function: foo` 格式的错误信息
+    pub fn parse(function: &str, raw: &str) -> VimError {
+        let raw = raw.lines().next().unwrap_or_default().trim();
+        let (code, message) = match find_error_code(raw) {
+            Some((code, end)) => (Some(code), raw[end..].trim()),
+            None => (None, raw),
+        };
+        VimError {
+            function: function.to_string(),
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for VimError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{}: E{}: {}", self.function, code, self.message),
+            None => write!(f, "{}: {}", self.function, self.message),
+        }
+    }
+}
+
+impl std::error::Error for VimError {}
+
+impl From<VimError> for Error {
+    fn from(err: VimError) -> Self {
+        Error::external(err)
+    }
+}
+
+/// 取出最内层的错误信息
+fn lua_error_message(err: &Error) -> String {
+    match err {
+        Error::RuntimeError(message) => message.clone(),
+        Error::CallbackError { cause, .. } => lua_error_message(cause),
+        Error::WithContext { cause, .. } => lua_error_message(cause),
+        err => err.to_string(),
+    }
+}
+
+/// 查找 `E123:` 形式的错误码，返回错误码与其后的字节位置
+fn find_error_code(raw: &str) -> Option<(u32, usize)> {
+    let bytes = raw.as_bytes();
+    (0..bytes.len()).find_map(|start| {
+        let boundary = start == 0 || matches!(bytes[start - 1], b':' | b' ' | b'(');
+        if !boundary || bytes[start] != b'E' {
+            return None;
+        }
+        let digits = bytes[start + 1..]
+            .iter()
+            .take_while(|x| x.is_ascii_digit())
+            .count();
+        let end = start + 1 + digits;
+        if digits > 0 && bytes.get(end) == Some(&b':') {
+            let code = raw[start + 1..end].parse().ok()?;
+            Some((code, end + 1))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        let err = VimError::parse(
+            "foo",
+            "[string \"...\"]:1: Vim:E117: Unknown function: foo\nstack traceback:",
+        );
+        assert_eq!(err.code, Some(117));
+        assert_eq!(err.message, "Unknown function: foo");
+        let err = VimError::parse("nvim_buf_get_lines", "Invalid buffer id: 99");
+        assert_eq!(err.code, None);
+        assert_eq!(err.to_string(), "nvim_buf_get_lines: Invalid buffer id: 99");
+    }
+}
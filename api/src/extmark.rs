@@ -0,0 +1,58 @@
+use mlua::Lua;
+use mlua::prelude::LuaResult;
+use serde::Serialize;
+
+use crate::builtin_fn::call_api;
+use crate::range::Range;
+
+pub use crate::diagnostic::namespace;
+
+/// nvim_buf_set_extmark 的高亮参数
+#[derive(Serialize)]
+struct HighlightOpts<'a> {
+    end_row: usize,
+    end_col: usize,
+    hl_group: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<u32>,
+}
+
+/// vim.api.nvim_buf_set_extmark
+/// 高亮 range，返回 extmark id，priority 为 None 时使用默认优先级
+pub fn highlight(
+    lua: &Lua,
+    namespace: u32,
+    buffer: usize,
+    range: Range,
+    group: &str,
+    priority: Option<u32>,
+) -> LuaResult<u32> {
+    let opts = HighlightOpts {
+        end_row: range.end.row,
+        end_col: range.end.col,
+        hl_group: group,
+        priority,
+    };
+    call_api(
+        lua,
+        "nvim_buf_set_extmark",
+        (buffer, namespace, range.start.row, range.start.col, opts),
+    )
+}
+
+/// vim.api.nvim_buf_clear_namespace
+/// 清除行范围 [start_row, end_row) 内的 extmark，end_row 为 None 时清除到末尾
+pub fn clear(
+    lua: &Lua,
+    namespace: u32,
+    buffer: usize,
+    start_row: usize,
+    end_row: Option<usize>,
+) -> LuaResult<()> {
+    let end_row = end_row.map_or(-1, |row| row as i64);
+    call_api(
+        lua,
+        "nvim_buf_clear_namespace",
+        (buffer, namespace, start_row, end_row),
+    )
+}
@@ -0,0 +1,326 @@
+use mlua::prelude::{LuaError, LuaFunction, LuaResult, LuaValue};
+use mlua::{Lua, LuaSerdeExt};
+use serde::{Deserialize, Serialize};
+
+use crate::command::Command;
+use crate::option::{self, Scope};
+use crate::range::Range;
+
+/// 'foldmethod'
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FoldMethod {
+    #[default]
+    Manual,
+    Indent,
+    Expr,
+    Marker,
+    Syntax,
+    Diff,
+}
+
+impl FoldMethod {
+    pub fn parse(value: &str) -> Option<FoldMethod> {
+        match value {
+            "manual" => Some(FoldMethod::Manual),
+            "indent" => Some(FoldMethod::Indent),
+            "expr" => Some(FoldMethod::Expr),
+            "marker" => Some(FoldMethod::Marker),
+            "syntax" => Some(FoldMethod::Syntax),
+            "diff" => Some(FoldMethod::Diff),
+            _ => None,
+        }
+    }
+
+    /// 是否可以通过 `:fold` 创建折叠
+    pub fn can_create(&self) -> bool {
+        matches!(self, FoldMethod::Manual | FoldMethod::Marker)
+    }
+}
+
+impl std::fmt::Display for FoldMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FoldMethod::Manual => "manual",
+            FoldMethod::Indent => "indent",
+            FoldMethod::Expr => "expr",
+            FoldMethod::Marker => "marker",
+            FoldMethod::Syntax => "syntax",
+            FoldMethod::Diff => "diff",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// 窗口的折叠状态，折叠为整行范围 [start_row, end_row)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FoldState {
+    pub method: FoldMethod,
+    /// 手动折叠，其他折叠方式由 vim 计算，不需要保存
+    pub folds: Vec<Range>,
+    /// 关闭的最外层折叠
+    pub closed: Vec<Range>,
+}
+
+const LEVEL: &str = r#"
+local win, lnum = ...
+return vim.api.nvim_win_call(win, function()
+  return vim.fn.foldlevel(lnum)
+end)
+"#;
+
+const CLOSED: &str = r#"
+local win = ...
+return vim.api.nvim_win_call(win, function()
+  local folds = {}
+  local lnum, last = 1, vim.fn.line("$")
+  while lnum <= last do
+    local start = vim.fn.foldclosed(lnum)
+    if start == -1 then
+      lnum = lnum + 1
+    else
+      local stop = vim.fn.foldclosedend(lnum)
+      folds[#folds + 1] = { start - 1, stop }
+      lnum = stop + 1
+    end
+  end
+  return folds
+end)
+"#;
+
+/// 关闭全部折叠后逐层打开，依次记录每个折叠，最后恢复关闭状态与视图
+const MANUAL_FOLDS: &str = r#"
+local win, closed = ...
+return vim.api.nvim_win_call(win, function()
+  local view = vim.fn.winsaveview()
+  vim.cmd("silent! %foldclose!")
+  local folds = {}
+  local lnum, last = 1, vim.fn.line("$")
+  while lnum <= last do
+    local start = vim.fn.foldclosed(lnum)
+    if start == -1 then
+      lnum = lnum + 1
+    else
+      folds[#folds + 1] = { start - 1, vim.fn.foldclosedend(lnum) }
+      vim.cmd(start .. "foldopen")
+    end
+  end
+  close_folds(closed)
+  vim.fn.winrestview(view)
+  return folds
+end)
+"#;
+
+/// `:fold` 创建的折叠是关闭的，且范围会扩展到涉及的整个关闭折叠
+/// 因此由内向外创建，并在创建后立即打开，避免嵌套折叠被合并
+const RESTORE: &str = r#"
+local win, folds, closed = ...
+vim.api.nvim_win_call(win, function()
+  local view = vim.fn.winsaveview()
+  if folds then
+    vim.cmd("silent! normal! zE")
+    for _, fold in ipairs(folds) do
+      local range = string.format("%d,%d", fold.start.line + 1, fold["end"].line)
+      vim.cmd(range .. "fold")
+      vim.cmd("silent! " .. range .. "foldopen!")
+    end
+  end
+  close_folds(closed)
+  vim.fn.winrestview(view)
+end)
+"#;
+
+/// 打开全部折叠后逐层关闭，直到关闭的折叠恰好为目标范围
+const PRELUDE: &str = r#"
+local function close_folds(closed)
+  vim.cmd("silent! %foldopen!")
+  for _, fold in ipairs(closed) do
+    local lnum = fold.start.line + 1
+    for _ = 1, vim.fn.foldlevel(lnum) do
+      if vim.fn.foldclosed(lnum) == lnum and vim.fn.foldclosedend(lnum) == fold["end"].line then
+        break
+      end
+      vim.cmd(string.format("silent! %dfoldclose", lnum))
+    end
+  end
+end
+"#;
+
+/// 窗口的 'foldmethod'，0 表示当前窗口
+pub fn method(lua: &Lua, window: usize) -> LuaResult<FoldMethod> {
+    let method: String = option::get(lua, "foldmethod", Scope::Window(window))?;
+    FoldMethod::parse(method.as_str())
+        .ok_or_else(|| LuaError::runtime(format!("unknown foldmethod: {method}")))
+}
+
+pub fn set_method(lua: &Lua, window: usize, method: FoldMethod) -> LuaResult<()> {
+    option::set(lua, "foldmethod", Scope::Window(window), method.to_string())
+}
+
+/// vim.fn.foldlevel，行从 0 开始
+pub fn level(lua: &Lua, window: usize, row: usize) -> LuaResult<usize> {
+    call(lua, LEVEL, (window, row + 1))
+}
+
+/// 关闭的最外层折叠
+pub fn closed(lua: &Lua, window: usize) -> LuaResult<Vec<Range>> {
+    let folds: Vec<(usize, usize)> = call(lua, CLOSED, window)?;
+    Ok(folds
+        .into_iter()
+        .map(|(start, end)| Range::lines(start, end))
+        .collect())
+}
+
+/// 为 [start_row, end_row) 创建折叠，仅支持 manual / marker
+pub fn create(lua: &Lua, window: usize, start_row: usize, end_row: usize) -> LuaResult<()> {
+    let method = method(lua, window)?;
+    if !method.can_create() {
+        return Err(LuaError::runtime(format!(
+            "cannot create fold with foldmethod={method}"
+        )));
+    }
+    Command::new("fold")
+        .lines(start_row, end_row)
+        .exec_in(lua, window)
+}
+
+/// 打开 [start_row, end_row) 中的折叠，recursive 为 true 时打开全部层级
+pub fn open(
+    lua: &Lua,
+    window: usize,
+    start_row: usize,
+    end_row: usize,
+    recursive: bool,
+) -> LuaResult<()> {
+    let command = Command::new("foldopen")
+        .lines(start_row, end_row)
+        .silent_errors();
+    let command = if recursive { command.bang() } else { command };
+    command.exec_in(lua, window)
+}
+
+/// 关闭 [start_row, end_row) 中的折叠，recursive 为 true 时关闭全部层级
+pub fn close(
+    lua: &Lua,
+    window: usize,
+    start_row: usize,
+    end_row: usize,
+    recursive: bool,
+) -> LuaResult<()> {
+    let command = Command::new("foldclose")
+        .lines(start_row, end_row)
+        .silent_errors();
+    let command = if recursive { command.bang() } else { command };
+    command.exec_in(lua, window)
+}
+
+/// 删除窗口中的全部手动折叠
+pub fn clear(lua: &Lua, window: usize) -> LuaResult<()> {
+    Command::new("normal")
+        .bang()
+        .arg("zE")
+        .silent_errors()
+        .exec_in(lua, window)
+}
+
+/// 保存窗口的折叠状态
+pub fn save(lua: &Lua, window: usize) -> LuaResult<FoldState> {
+    let method = method(lua, window)?;
+    let closed = closed(lua, window)?;
+    let folds = if method == FoldMethod::Manual {
+        let folds: Vec<(usize, usize)> = call(lua, MANUAL_FOLDS, (window, lua.to_value(&closed)?))?;
+        folds
+            .into_iter()
+            .map(|(start, end)| Range::lines(start, end))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(FoldState {
+        method,
+        folds,
+        closed,
+    })
+}
+
+/// 恢复窗口的折叠状态，保持视图不变
+pub fn restore(lua: &Lua, window: usize, state: &FoldState) -> LuaResult<()> {
+    set_method(lua, window, state.method)?;
+    let folds = if state.method == FoldMethod::Manual {
+        lua.to_value(&creation_order(&state.folds))?
+    } else {
+        LuaValue::Nil
+    };
+    let _: Option<()> = call(lua, RESTORE, (window, folds, lua.to_value(&state.closed)?))?;
+    Ok(())
+}
+
+/// 折叠的创建顺序，被包含的折叠先于外层折叠创建
+fn creation_order(folds: &[Range]) -> Vec<Range> {
+    let mut folds = folds.to_vec();
+    folds.sort_by_key(|fold| fold.end.row - fold.start.row);
+    folds
+}
+
+fn call<A, R>(lua: &Lua, source: &str, args: A) -> LuaResult<R>
+where
+    A: mlua::IntoLuaMulti,
+    R: serde::de::DeserializeOwned,
+{
+    let func: LuaFunction = lua.load(format!("{PRELUDE}{source}")).into_function()?;
+    lua.from_value(func.call::<LuaValue>(args)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_method_works() {
+        for method in [FoldMethod::Manual, FoldMethod::Expr, FoldMethod::Diff] {
+            assert_eq!(FoldMethod::parse(method.to_string().as_str()), Some(method));
+        }
+        assert_eq!(FoldMethod::parse("unknown"), None);
+        assert!(FoldMethod::Marker.can_create());
+        assert!(!FoldMethod::Indent.can_create());
+    }
+
+    /// 模拟 `:fold`：新折叠是关闭的，范围扩展到涉及的整个关闭折叠
+    fn fold(created: &mut Vec<(Range, bool)>, range: Range, open: bool) {
+        let (mut start, mut end) = (range.start.row, range.end.row);
+        for (fold, closed) in created.iter() {
+            if *closed && fold.start.row < end && start < fold.end.row {
+                start = start.min(fold.start.row);
+                end = end.max(fold.end.row);
+            }
+        }
+        created.push((Range::lines(start, end), !open));
+    }
+
+    #[test]
+    fn nested_folds_round_trip() {
+        // save 记录的顺序为由外向内
+        let folds = vec![Range::lines(0, 10), Range::lines(2, 5), Range::lines(6, 8)];
+        let mut created = Vec::new();
+        for range in creation_order(&folds) {
+            fold(&mut created, range, false);
+        }
+        let mut restored = created.into_iter().map(|(x, _)| x).collect::<Vec<_>>();
+        restored.sort_by_key(|x| (x.start.row, x.end.row));
+        assert_eq!(restored, folds);
+
+        // 保存的顺序直接创建时嵌套折叠会被扩展为外层折叠
+        let mut created = Vec::new();
+        for range in folds.iter() {
+            fold(&mut created, *range, false);
+        }
+        assert_eq!(created[1].0, Range::lines(0, 10));
+        // 创建后立即打开时顺序不影响结果
+        let mut created = Vec::new();
+        for range in folds.iter() {
+            fold(&mut created, *range, true);
+        }
+        let restored = created.into_iter().map(|(x, _)| x).collect::<Vec<_>>();
+        assert_eq!(restored, folds);
+    }
+}
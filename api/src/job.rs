@@ -0,0 +1,380 @@
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mlua::prelude::*;
+
+use crate::builtin_fn::call_api;
+use crate::quickfix::{self, Action, ListKind, ListWhat};
+use crate::timer::Timer;
+
+/// 主线程轮询子进程输出的间隔(毫秒)
+const POLL_INTERVAL: u64 = 20;
+/// 工作线程检查子进程状态的间隔
+const WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 子进程事件，在主线程中回调
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobEvent {
+    Stdout(String),
+    Stderr(String),
+    Exit(JobExit),
+}
+
+/// 子进程退出状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobExit {
+    /// 退出码，被信号终止时为 None
+    pub code: Option<i32>,
+    /// 是否被 kill
+    pub killed: bool,
+    /// 是否超时
+    pub timed_out: bool,
+}
+
+/// 子进程输出的去向
+#[derive(Debug, Clone, Default)]
+pub enum JobOutput {
+    /// 仅回调
+    #[default]
+    None,
+    /// 追加到 buffer
+    Buffer(usize),
+    /// 追加到新建的 quickfix 列表，按 errorformat 解析
+    Quickfix { title: String },
+}
+
+/// 子进程参数
+#[derive(Debug, Clone, Default)]
+pub struct JobOpts {
+    /// 工作目录
+    pub cwd: Option<PathBuf>,
+    /// 额外的环境变量
+    pub env: Vec<(String, String)>,
+    /// 是否保留 stdin 用于 `Job::send`
+    pub stdin: bool,
+    /// 超时后 kill 子进程
+    pub timeout: Option<Duration>,
+    /// stdout/stderr 输出去向
+    pub output: JobOutput,
+}
+
+/// 运行中的子进程
+#[derive(Clone)]
+pub struct Job {
+    pid: u32,
+    stdin: Arc<Mutex<Option<ChildStdin>>>,
+    kill: Arc<AtomicBool>,
+}
+
+impl Job {
+    /// 在工作线程中启动子进程，输出逐行回调到主线程
+    pub fn spawn<I, S, F>(
+        lua: &Lua,
+        program: &str,
+        args: I,
+        opts: JobOpts,
+        on_event: F,
+    ) -> LuaResult<Job>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+        F: FnMut(&Lua, JobEvent) -> LuaResult<()> + 'static,
+    {
+        let output = JobSink::try_new(lua, opts.output.clone())?;
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .envs(opts.env.iter().map(|(k, v)| (k, v)))
+            .stdin(if opts.stdin {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(cwd) = opts.cwd.as_ref() {
+            command.current_dir(cwd);
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|err| LuaError::runtime(format!("spawn job {program} failed: {err}")))?;
+        let job = Job {
+            pid: child.id(),
+            stdin: Arc::new(Mutex::new(child.stdin.take())),
+            kill: Arc::new(AtomicBool::new(false)),
+        };
+        let (sender, receiver) = mpsc::channel();
+        let readers = [
+            child
+                .stdout
+                .take()
+                .map(|x| read_lines(x, sender.clone(), JobEvent::Stdout)),
+            child
+                .stderr
+                .take()
+                .map(|x| read_lines(x, sender.clone(), JobEvent::Stderr)),
+        ];
+        let kill = job.kill.clone();
+        let deadline = opts.timeout.map(|x| Instant::now() + x);
+        thread::spawn(move || {
+            let mut exit = JobExit {
+                code: None,
+                killed: false,
+                timed_out: false,
+            };
+            loop {
+                if deadline.is_some_and(|x| Instant::now() >= x) {
+                    exit.timed_out = true;
+                    let _ = child.kill();
+                } else if kill.load(Ordering::Relaxed) {
+                    exit.killed = true;
+                    let _ = child.kill();
+                }
+                match child.try_wait() {
+                    Ok(Some(status)) => {
+                        exit.code = status.code();
+                        break;
+                    }
+                    Ok(None) => thread::sleep(WAIT_INTERVAL),
+                    Err(_) => break,
+                }
+            }
+            readers.into_iter().flatten().for_each(|x| {
+                let _ = x.join();
+            });
+            let _ = sender.send(JobEvent::Exit(exit));
+        });
+        poll(lua, receiver, output, on_event)?;
+        Ok(job)
+    }
+
+    /// 子进程 pid
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// 写入 stdin
+    pub fn send(&self, data: &str) -> LuaResult<()> {
+        let mut stdin = self
+            .stdin
+            .lock()
+            .map_err(|_| LuaError::runtime("job stdin lock failed!"))?;
+        match stdin.as_mut() {
+            Some(stdin) => Ok(stdin.write_all(data.as_bytes())?),
+            None => Err(LuaError::runtime("job stdin is closed!")),
+        }
+    }
+
+    /// 关闭 stdin，子进程读到 EOF
+    pub fn close_stdin(&self) {
+        if let Ok(mut stdin) = self.stdin.lock() {
+            stdin.take();
+        }
+    }
+
+    /// 终止子进程
+    pub fn kill(&self) {
+        self.kill.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 输出写入 buffer / quickfix
+enum JobSink {
+    None,
+    Buffer { buffer: usize, empty: bool },
+    Quickfix { id: usize },
+}
+
+impl JobSink {
+    fn try_new(lua: &Lua, output: JobOutput) -> LuaResult<JobSink> {
+        Ok(match output {
+            JobOutput::None => JobSink::None,
+            JobOutput::Buffer(buffer) => {
+                let lines: Vec<String> =
+                    call_api(lua, "nvim_buf_get_lines", (buffer, 0, -1, false))?;
+                JobSink::Buffer {
+                    buffer,
+                    empty: lines.len() == 1 && lines[0].is_empty(),
+                }
+            }
+            JobOutput::Quickfix { title } => {
+                let id = quickfix::create(lua, ListKind::Quickfix, title.as_str(), vec![], None)?;
+                JobSink::Quickfix { id }
+            }
+        })
+    }
+
+    fn write(&mut self, lua: &Lua, lines: &[String]) -> LuaResult<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        match self {
+            JobSink::None => {}
+            JobSink::Buffer { buffer, empty } => {
+                // 空 buffer 首次写入时替换掉唯一的空行
+                let (start, end) = if *empty { (0, 1) } else { (-1, -1) };
+                *empty = false;
+                let _: () = call_api(
+                    lua,
+                    "nvim_buf_set_lines",
+                    (*buffer, start, end, false, lines),
+                )?;
+            }
+            JobSink::Quickfix { id } => {
+                let what = ListWhat {
+                    id: Some(*id),
+                    lines: Some(lines.to_vec()),
+                    ..Default::default()
+                };
+                quickfix::set_list(lua, ListKind::Quickfix, Action::Append, &what)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 新建 scratch buffer，用于接收子进程输出
+pub fn scratch_buffer(lua: &Lua) -> LuaResult<usize> {
+    call_api(lua, "nvim_create_buf", (false, true))
+}
+
+/// 工作线程中逐行读取输出
+fn read_lines<R, F>(reader: R, sender: Sender<JobEvent>, event: F) -> thread::JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    F: Fn(String) -> JobEvent + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buffer = Vec::new();
+        while let Ok(size) = reader.read_until(b'\n', &mut buffer) {
+            if size == 0 {
+                break;
+            }
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim_end_matches(['\n', '\r']).to_string();
+            buffer.clear();
+            if sender.send(event(line)).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// 在工作线程中执行 work，完成后在主线程中回调结果
+/// 用于文件扫描等耗时且不需要调用 nvim api 的任务
+pub fn background<T, W, F>(lua: &Lua, work: W, callback: F) -> LuaResult<()>
+where
+    T: Send + 'static,
+    W: FnOnce() -> T + Send + 'static,
+    F: FnOnce(&Lua, T) -> LuaResult<()> + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(work());
+    });
+    let timer = Timer::new(lua)?;
+    let stop_timer = timer.clone();
+    let mut callback = Some(callback);
+    timer.start(lua, 0, POLL_INTERVAL, move |lua| {
+        // 定时器关闭前可能已有排队的回调
+        if callback.is_none() {
+            return Ok(());
+        }
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return Ok(()),
+            Err(TryRecvError::Disconnected) => {
+                callback = None;
+                stop_timer.close(lua)?;
+                return Err(LuaError::runtime("background task panicked!"));
+            }
+        };
+        stop_timer.close(lua)?;
+        match callback.take() {
+            Some(callback) => callback(lua, result),
+            None => Ok(()),
+        }
+    })
+}
+
+/// 主线程通过定时器轮询子进程事件
+fn poll<F>(
+    lua: &Lua,
+    receiver: Receiver<JobEvent>,
+    mut output: JobSink,
+    mut on_event: F,
+) -> LuaResult<()>
+where
+    F: FnMut(&Lua, JobEvent) -> LuaResult<()> + 'static,
+{
+    let timer = Timer::new(lua)?;
+    let stop_timer = timer.clone();
+    let mut finished = false;
+    timer.start(lua, 0, POLL_INTERVAL, move |lua| {
+        // 定时器关闭前可能已有排队的回调
+        if finished {
+            return Ok(());
+        }
+        match drain(lua, &receiver, &mut output, &mut on_event) {
+            Ok(Some(exit)) => {
+                finished = true;
+                stop_timer.close(lua)?;
+                on_event(lua, JobEvent::Exit(exit))
+            }
+            Ok(None) => Ok(()),
+            // 回调出错时停止轮询，避免定时器持续触发
+            Err(err) => {
+                finished = true;
+                stop_timer.close(lua)?;
+                Err(err)
+            }
+        }
+    })
+}
+
+/// 处理已排队的事件，子进程退出时返回退出信息
+fn drain<F>(
+    lua: &Lua,
+    receiver: &Receiver<JobEvent>,
+    output: &mut JobSink,
+    on_event: &mut F,
+) -> LuaResult<Option<JobExit>>
+where
+    F: FnMut(&Lua, JobEvent) -> LuaResult<()>,
+{
+    let mut lines = Vec::new();
+    let mut exit = None;
+    loop {
+        match receiver.try_recv() {
+            Ok(JobEvent::Exit(code)) => {
+                exit = Some(code);
+                break;
+            }
+            Ok(event) => {
+                if let JobEvent::Stdout(line) | JobEvent::Stderr(line) = &event {
+                    lines.push(line.clone());
+                }
+                on_event(lua, event)?;
+            }
+            Err(TryRecvError::Empty) => break,
+            Err(TryRecvError::Disconnected) => {
+                exit = Some(JobExit {
+                    code: None,
+                    killed: false,
+                    timed_out: false,
+                });
+                break;
+            }
+        }
+    }
+    output.write(lua, &lines)?;
+    Ok(exit)
+}
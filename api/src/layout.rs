@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use mlua::prelude::{LuaFunction, LuaResult, LuaValue};
+use mlua::{Lua, LuaSerdeExt};
+use serde::{Deserialize, Serialize};
+
+use crate::builtin_fn::{call_api, call_fn};
+use crate::range::Point;
+
+/// vim.fn.winsaveview
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct View {
+    pub lnum: usize,
+    pub col: usize,
+    #[serde(default)]
+    pub coladd: usize,
+    #[serde(default)]
+    pub curswant: usize,
+    pub topline: usize,
+    #[serde(default)]
+    pub topfill: usize,
+    #[serde(default)]
+    pub leftcol: usize,
+    #[serde(default)]
+    pub skipcol: usize,
+}
+
+/// 窗口
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Window {
+    pub id: usize,
+    pub buffer: usize,
+    /// buffer 文件名，恢复时优先按文件名打开
+    pub name: String,
+    /// 光标位置，行列从 0 开始
+    pub cursor: Point,
+    pub view: View,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// vim.fn.winlayout 的窗口树
+/// row 为左右排列，col 为上下排列
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    Leaf(Window),
+    Row(Vec<Layout>),
+    Col(Vec<Layout>),
+}
+
+impl Layout {
+    /// 全部窗口，从左上到右下
+    pub fn windows(&self) -> Vec<&Window> {
+        match self {
+            Layout::Leaf(window) => vec![window],
+            Layout::Row(children) | Layout::Col(children) => {
+                children.iter().flat_map(|x| x.windows()).collect()
+            }
+        }
+    }
+}
+
+/// 标签页
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Tab {
+    pub id: usize,
+    pub number: usize,
+    pub current_window: usize,
+    pub layout: Layout,
+}
+
+const TAB_LAYOUT: &str = r#"
+local tabpage = ...
+if tabpage == 0 then
+  tabpage = vim.api.nvim_get_current_tabpage()
+end
+local function window(win)
+  local buf = vim.api.nvim_win_get_buf(win)
+  local cursor = vim.api.nvim_win_get_cursor(win)
+  return {
+    id = win,
+    buffer = buf,
+    name = vim.api.nvim_buf_get_name(buf),
+    cursor = { line = cursor[1] - 1, character = cursor[2] },
+    view = vim.api.nvim_win_call(win, vim.fn.winsaveview),
+    width = vim.api.nvim_win_get_width(win),
+    height = vim.api.nvim_win_get_height(win),
+  }
+end
+local function walk(node)
+  if node[1] == "leaf" then
+    return { leaf = window(node[2]) }
+  end
+  local children = {}
+  for i, child in ipairs(node[2]) do
+    children[i] = walk(child)
+  end
+  return { [node[1]] = children }
+end
+local number = vim.api.nvim_tabpage_get_number(tabpage)
+return {
+  id = tabpage,
+  number = number,
+  current_window = vim.api.nvim_tabpage_get_win(tabpage),
+  layout = walk(vim.fn.winlayout(number)),
+}
+"#;
+
+/// 标签页的窗口布局，不包含浮动窗口，0 表示当前标签页
+pub fn tab_layout(lua: &Lua, tabpage: usize) -> LuaResult<Tab> {
+    let layout: LuaFunction = lua.load(TAB_LAYOUT).into_function()?;
+    lua.from_value(layout.call::<LuaValue>(tabpage)?)
+}
+
+/// 全部标签页的窗口布局
+pub fn tabs(lua: &Lua) -> LuaResult<Vec<Tab>> {
+    let tabpages: Vec<usize> = call_api(lua, "nvim_list_tabpages", ())?;
+    tabpages
+        .into_iter()
+        .map(|tabpage| tab_layout(lua, tabpage))
+        .collect()
+}
+
+/// 在当前标签页中重建布局，其他窗口会被关闭
+/// 返回原窗口 id 到新窗口 id 的映射
+pub fn restore(lua: &Lua, layout: &Layout) -> LuaResult<HashMap<usize, usize>> {
+    let _: () = call_api(lua, "nvim_command", ("only!",))?;
+    let root: usize = call_api(lua, "nvim_get_current_win", ())?;
+    let mut windows = HashMap::new();
+    split(lua, root, layout, &mut windows)?;
+    // 调整一个窗口的大小会影响相邻窗口，第二遍修正
+    for _ in 0..2 {
+        for window in layout.windows() {
+            let id = windows[&window.id];
+            let _: () = call_api(lua, "nvim_win_set_width", (id, window.width))?;
+            let _: () = call_api(lua, "nvim_win_set_height", (id, window.height))?;
+        }
+    }
+    for window in layout.windows() {
+        restore_view(lua, windows[&window.id], &window.view)?;
+    }
+    Ok(windows)
+}
+
+/// 关闭其他标签页后按顺序重建全部标签页，最后回到第一个标签页
+pub fn restore_tabs(lua: &Lua, tabs: &[Tab]) -> LuaResult<()> {
+    let _: () = call_api(lua, "nvim_command", ("tabonly!",))?;
+    let mut first = None;
+    for (index, tab) in tabs.iter().enumerate() {
+        if index > 0 {
+            let _: () = call_api(lua, "nvim_command", ("tabnew",))?;
+        }
+        let windows = restore(lua, &tab.layout)?;
+        if let Some(&window) = windows.get(&tab.current_window) {
+            let _: () = call_api(lua, "nvim_set_current_win", (window,))?;
+        }
+        if first.is_none() {
+            first = Some(call_api::<_, usize>(lua, "nvim_get_current_tabpage", ())?);
+        }
+    }
+    match first {
+        Some(tabpage) => call_api(lua, "nvim_set_current_tabpage", (tabpage,)),
+        None => Ok(()),
+    }
+}
+
+/// 先为同一层的子节点切分出窗口，再递归切分每个子节点
+fn split(
+    lua: &Lua,
+    window: usize,
+    layout: &Layout,
+    windows: &mut HashMap<usize, usize>,
+) -> LuaResult<()> {
+    #[derive(Serialize)]
+    struct SplitConfig {
+        split: &'static str,
+        win: usize,
+    }
+    let (direction, children) = match layout {
+        Layout::Leaf(leaf) => {
+            if let Some(buffer) = resolve_buffer(lua, leaf)? {
+                let _: () = call_api(lua, "nvim_win_set_buf", (window, buffer))?;
+            }
+            windows.insert(leaf.id, window);
+            return Ok(());
+        }
+        Layout::Row(children) => ("right", children),
+        Layout::Col(children) => ("below", children),
+    };
+    let buffer: usize = call_api(lua, "nvim_win_get_buf", (window,))?;
+    let mut targets = vec![window];
+    for _ in 1..children.len() {
+        let config = SplitConfig {
+            split: direction,
+            win: targets[targets.len() - 1],
+        };
+        targets.push(call_api(lua, "nvim_open_win", (buffer, false, config))?);
+    }
+    for (child, target) in children.iter().zip(targets) {
+        split(lua, target, child, windows)?;
+    }
+    Ok(())
+}
+
+/// 按文件名加载 buffer，没有文件名时使用仍然有效的原 buffer
+fn resolve_buffer(lua: &Lua, window: &Window) -> LuaResult<Option<usize>> {
+    if !window.name.is_empty() {
+        let buffer: usize = call_fn(lua, "bufadd", (window.name.as_str(),))?;
+        let _: serde_json::Value = call_fn(lua, "bufload", (buffer,))?;
+        return Ok(Some(buffer));
+    }
+    let valid: bool = call_api(lua, "nvim_buf_is_valid", (window.buffer,))?;
+    Ok(valid.then_some(window.buffer))
+}
+
+/// 在窗口中执行 winrestview
+fn restore_view(lua: &Lua, window: usize, view: &View) -> LuaResult<()> {
+    let view = lua.to_value(view)?;
+    let restore = lua.create_function(move |lua, ()| {
+        let winrestview: LuaFunction = lua.load("vim.fn.winrestview").eval()?;
+        winrestview.call::<()>(view.clone())
+    })?;
+    let win_call: LuaFunction = lua.load("vim.api.nvim_win_call").eval()?;
+    win_call.call((window, restore))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_deserialize_works() {
+        let window = |id| {
+            serde_json::json!({
+                "leaf": {
+                    "id": id, "buffer": 1, "name": "", "cursor": { "line": 0, "character": 0 },
+                    "view": { "lnum": 1, "col": 0, "topline": 1 }, "width": 80, "height": 20
+                }
+            })
+        };
+        let value =
+            serde_json::json!({ "row": [window(1000), { "col": [window(1001), window(1002)] }] });
+        let layout: Layout = serde_json::from_value(value).unwrap();
+        let ids = layout.windows().iter().map(|x| x.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![1000, 1001, 1002]);
+    }
+}
@@ -3,11 +3,36 @@ use mlua::Lua;
 
 pub mod buffer;
 pub mod builtin_fn;
+pub mod command;
+pub mod diagnostic;
+pub mod error;
+pub mod extmark;
+pub mod fold;
 pub mod func;
+pub mod job;
+pub mod layout;
+pub mod mark;
+pub mod mirror;
+pub mod option;
+pub mod quickfix;
+pub mod range;
+pub mod register;
+pub mod text_edit;
+pub mod timer;
+pub mod treesitter;
+pub mod ui;
 pub mod util;
 
+/// vim.api.nvim_exec2
+/// 执行 Ex 命令字符串，参数需要自行转义，优先使用 [`command::Command`]
 pub fn cmd(lua: &Lua, cmd: String) -> LuaResult<()> {
-    Ok(lua.load(format!("vim.cmd([[{cmd}]])")).eval()?)
+    #[derive(serde::Serialize)]
+    struct Opts {
+        output: bool,
+    }
+    let _: serde_json::Value =
+        builtin_fn::call_api(lua, "nvim_exec2", (cmd, Opts { output: false }))?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -0,0 +1,204 @@
+use mlua::prelude::{LuaFunction, LuaResult};
+use mlua::{Lua, LuaSerdeExt};
+use serde::{Deserialize, Serialize};
+
+use crate::builtin_fn::{call_api, call_fn};
+use crate::range::Point;
+
+/// 标记，位置行列从 0 开始
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Mark {
+    /// 标记名，例如 `a` / `A` / `"`
+    pub name: char,
+    pub buffer: usize,
+    pub point: Point,
+    /// 全局标记所在文件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+/// getmarklist 的返回项，pos 为 [bufnr, lnum, col, off]，行列从 1 开始
+#[derive(Deserialize)]
+struct RawMark {
+    mark: String,
+    pos: (usize, usize, usize, usize),
+    #[serde(default)]
+    file: Option<String>,
+}
+
+impl From<RawMark> for Mark {
+    fn from(raw: RawMark) -> Self {
+        let (buffer, lnum, col, _) = raw.pos;
+        Mark {
+            // mark 字段形如 `'a`
+            name: raw.mark.chars().last().unwrap_or_default(),
+            buffer,
+            point: Point::new(lnum.saturating_sub(1), col.saturating_sub(1)),
+            file: raw.file,
+        }
+    }
+}
+
+/// 跳转表 / 改变表中的一项，位置行列从 0 开始
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Jump {
+    pub buffer: usize,
+    pub point: Point,
+    /// buffer 已卸载时仍可以通过文件名恢复
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+}
+
+/// getjumplist / getchangelist 的返回项，lnum 从 1 开始，col 从 0 开始
+#[derive(Deserialize)]
+struct RawJump {
+    #[serde(default)]
+    bufnr: usize,
+    lnum: usize,
+    col: usize,
+    #[serde(default)]
+    filename: Option<String>,
+}
+
+impl RawJump {
+    fn into_jump(self, buffer: usize) -> Jump {
+        Jump {
+            buffer: if self.bufnr == 0 { buffer } else { self.bufnr },
+            point: Point::new(self.lnum.saturating_sub(1), self.col),
+            filename: self.filename,
+        }
+    }
+}
+
+/// 跳转表 / 改变表，current 为当前所在位置，等于 entries.len() 时表示位于末尾
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct JumpList {
+    pub entries: Vec<Jump>,
+    pub current: usize,
+}
+
+const RESTORE_JUMPS: &str = r#"
+local entries = ...
+local win = vim.api.nvim_get_current_win()
+local buf = vim.api.nvim_get_current_buf()
+local cursor = vim.api.nvim_win_get_cursor(win)
+local eventignore = vim.o.eventignore
+vim.o.eventignore = "all"
+vim.cmd("clearjumps")
+for _, entry in ipairs(entries) do
+  local bufnr = entry.filename and vim.fn.bufadd(entry.filename) or entry.buffer
+  if vim.api.nvim_buf_is_valid(bufnr) then
+    vim.fn.bufload(bufnr)
+    vim.api.nvim_win_set_buf(win, bufnr)
+    if pcall(vim.api.nvim_win_set_cursor, win, { entry.point.line + 1, entry.point.character }) then
+      vim.cmd("normal! m'")
+    end
+  end
+end
+vim.api.nvim_win_set_buf(win, buf)
+vim.api.nvim_win_set_cursor(win, cursor)
+vim.o.eventignore = eventignore
+"#;
+
+/// buffer 中的小写标记及特殊标记
+pub fn buffer_marks(lua: &Lua, buffer: usize) -> LuaResult<Vec<Mark>> {
+    let marks: Vec<RawMark> = call_fn(lua, "getmarklist", (buffer,))?;
+    Ok(marks.into_iter().map(Mark::from).collect())
+}
+
+/// 全局标记 (A-Z, 0-9)
+pub fn global_marks(lua: &Lua) -> LuaResult<Vec<Mark>> {
+    let marks: Vec<RawMark> = call_fn(lua, "getmarklist", ())?;
+    Ok(marks.into_iter().map(Mark::from).collect())
+}
+
+/// vim.api.nvim_buf_get_mark
+/// 标记不存在时返回 None
+pub fn get(lua: &Lua, buffer: usize, name: char) -> LuaResult<Option<Point>> {
+    let (row, col): (usize, usize) =
+        call_api(lua, "nvim_buf_get_mark", (buffer, name.to_string()))?;
+    Ok(row.checked_sub(1).map(|row| Point::new(row, col)))
+}
+
+/// vim.api.nvim_buf_set_mark
+/// 大写标记会成为指向该 buffer 的全局标记
+pub fn set(lua: &Lua, buffer: usize, name: char, point: Point) -> LuaResult<()> {
+    #[derive(Serialize)]
+    struct Opts {}
+    let _: bool = call_api(
+        lua,
+        "nvim_buf_set_mark",
+        (buffer, name.to_string(), point.row + 1, point.col, Opts {}),
+    )?;
+    Ok(())
+}
+
+/// 删除标记，大写标记通过 nvim_del_mark 删除
+pub fn delete(lua: &Lua, buffer: usize, name: char) -> LuaResult<bool> {
+    if name.is_ascii_uppercase() {
+        call_api(lua, "nvim_del_mark", (name.to_string(),))
+    } else {
+        call_api(lua, "nvim_buf_del_mark", (buffer, name.to_string()))
+    }
+}
+
+/// vim.fn.getjumplist
+/// window 为窗口 id，0 表示当前窗口
+pub fn jumplist(lua: &Lua, window: usize) -> LuaResult<JumpList> {
+    let (entries, current): (Vec<RawJump>, usize) = call_fn(lua, "getjumplist", (window,))?;
+    Ok(JumpList {
+        entries: entries.into_iter().map(|x| x.into_jump(0)).collect(),
+        current,
+    })
+}
+
+/// vim.fn.getchangelist
+pub fn changelist(lua: &Lua, buffer: usize) -> LuaResult<JumpList> {
+    let (entries, current): (Vec<RawJump>, usize) = call_fn(lua, "getchangelist", (buffer,))?;
+    Ok(JumpList {
+        entries: entries.into_iter().map(|x| x.into_jump(buffer)).collect(),
+        current,
+    })
+}
+
+/// 清空当前窗口的跳转表
+pub fn clear_jumps(lua: &Lua) -> LuaResult<()> {
+    call_api(lua, "nvim_command", ("clearjumps",))
+}
+
+/// 将当前光标位置加入跳转表
+pub fn push_jump(lua: &Lua) -> LuaResult<()> {
+    call_api(lua, "nvim_command", ("normal! m'",))
+}
+
+/// 在当前窗口中按顺序重建跳转表，buffer 失效的项通过文件名重新加载，无法恢复的项被忽略
+/// 恢复后位于跳转表末尾，光标与当前 buffer 保持不变
+pub fn restore_jumplist(lua: &Lua, jumplist: &JumpList) -> LuaResult<()> {
+    let restore: LuaFunction = lua.load(RESTORE_JUMPS).into_function()?;
+    restore.call(lua.to_value(&jumplist.entries)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_mark_works() {
+        let raw = RawMark {
+            mark: "'A".to_string(),
+            pos: (3, 10, 5, 0),
+            file: Some("src/lib.rs".to_string()),
+        };
+        let mark = Mark::from(raw);
+        assert_eq!(mark.name, 'A');
+        assert_eq!(mark.buffer, 3);
+        assert_eq!(mark.point, Point::new(9, 4));
+        let raw = RawJump {
+            bufnr: 0,
+            lnum: 1,
+            col: 0,
+            filename: None,
+        };
+        assert_eq!(raw.into_jump(2).buffer, 2);
+    }
+}
@@ -0,0 +1,269 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use mlua::prelude::*;
+use ropey::Rope;
+
+/// 一次行变更：原 [first_line, last_line) 被替换为 [first_line, new_last_line)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineChange {
+    pub first_line: usize,
+    pub last_line: usize,
+    pub new_last_line: usize,
+    pub changedtick: u64,
+}
+
+type Listener = Rc<dyn Fn(&Rope, &LineChange)>;
+
+/// on_lines 回调参数: ("lines", bufnr, changedtick, firstline, lastline, new_lastline, ...)
+type OnLinesArgs = (LuaValue, LuaValue, u64, usize, usize, usize);
+
+struct MirrorState {
+    buffer: usize,
+    rope: Rope,
+    changedtick: u64,
+    attached: bool,
+    listeners: Vec<Listener>,
+}
+
+/// buffer 内容在 rust 侧的 rope 镜像
+/// 通过 nvim_buf_attach 增量同步，每行以 `\n` 结尾
+#[derive(Clone)]
+pub struct BufferMirror {
+    state: Rc<RefCell<MirrorState>>,
+}
+
+impl BufferMirror {
+    /// vim.api.nvim_buf_attach
+    /// 挂载到 buffer 并建立镜像，buffer 为 0 时使用当前 buffer
+    pub fn attach(lua: &Lua, buffer: usize) -> LuaResult<BufferMirror> {
+        let buffer = if buffer == 0 {
+            lua.load("vim.api.nvim_get_current_buf()").eval()?
+        } else {
+            buffer
+        };
+        let mut rope = Rope::new();
+        replace_lines(&mut rope, 0, 0, &buffer_lines(lua, buffer)?);
+        let mirror = BufferMirror {
+            state: Rc::new(RefCell::new(MirrorState {
+                buffer,
+                rope,
+                changedtick: changedtick(lua, buffer)?,
+                attached: true,
+                listeners: Vec::new(),
+            })),
+        };
+        let callbacks = lua.create_table()?;
+        let on_lines = mirror.clone();
+        callbacks.set(
+            "on_lines",
+            lua.create_function(
+                move |lua, (_, _, tick, first, last, new_last): OnLinesArgs| {
+                    on_lines.on_lines(lua, tick, first, last, new_last)
+                },
+            )?,
+        )?;
+        let on_reload = mirror.clone();
+        callbacks.set(
+            "on_reload",
+            lua.create_function(move |lua, ()| on_reload.reload(lua))?,
+        )?;
+        let on_detach = mirror.clone();
+        callbacks.set(
+            "on_detach",
+            lua.create_function(move |_, ()| {
+                on_detach.state.borrow_mut().attached = false;
+                Ok(())
+            })?,
+        )?;
+        let buf_attach: LuaFunction = lua.load("vim.api.nvim_buf_attach").eval()?;
+        if !buf_attach.call::<bool>((buffer, false, callbacks))? {
+            return Err(LuaError::runtime(format!("attach buffer {buffer} failed!")));
+        }
+        Ok(mirror)
+    }
+
+    /// buffer 编号
+    pub fn buffer(&self) -> usize {
+        self.state.borrow().buffer
+    }
+
+    /// 最近一次同步的 changedtick
+    pub fn changedtick(&self) -> u64 {
+        self.state.borrow().changedtick
+    }
+
+    /// 是否仍挂载在 buffer 上
+    pub fn is_attached(&self) -> bool {
+        self.state.borrow().attached
+    }
+
+    /// 当前内容快照，rope 克隆为 O(1)
+    pub fn snapshot(&self) -> Rope {
+        self.state.borrow().rope.clone()
+    }
+
+    /// 行数
+    pub fn line_count(&self) -> usize {
+        line_count(&self.state.borrow().rope)
+    }
+
+    /// 获取一行内容(0-based)，不含换行符
+    pub fn line(&self, index: usize) -> Option<String> {
+        line(&self.state.borrow().rope, index)
+    }
+
+    /// 订阅变更，回调在镜像更新后执行
+    pub fn subscribe<F>(&self, listener: F)
+    where
+        F: Fn(&Rope, &LineChange) + 'static,
+    {
+        self.state.borrow_mut().listeners.push(Rc::new(listener));
+    }
+
+    /// 取消挂载，下一次回调时生效
+    pub fn detach(&self) {
+        let mut state = self.state.borrow_mut();
+        state.attached = false;
+        state.listeners.clear();
+    }
+
+    fn on_lines(
+        &self,
+        lua: &Lua,
+        changedtick: u64,
+        first_line: usize,
+        last_line: usize,
+        new_last_line: usize,
+    ) -> LuaResult<bool> {
+        if !self.is_attached() {
+            return Ok(true);
+        }
+        let buffer = self.buffer();
+        let lines = buffer_lines_range(lua, buffer, first_line as i64, new_last_line as i64)?;
+        let change = LineChange {
+            first_line,
+            last_line,
+            new_last_line,
+            changedtick,
+        };
+        let rope = {
+            let mut state = self.state.borrow_mut();
+            if last_line > line_count(&state.rope) {
+                // 镜像已失步，整体重建
+                drop(state);
+                return self.reload(lua).map(|_| false);
+            }
+            replace_lines(&mut state.rope, first_line, last_line, &lines);
+            state.changedtick = changedtick;
+            state.rope.clone()
+        };
+        self.notify(&rope, &change);
+        Ok(false)
+    }
+
+    fn reload(&self, lua: &Lua) -> LuaResult<()> {
+        let buffer = self.buffer();
+        let lines = buffer_lines(lua, buffer)?;
+        let changedtick = changedtick(lua, buffer)?;
+        let (rope, change) = {
+            let mut state = self.state.borrow_mut();
+            let last_line = line_count(&state.rope);
+            let mut rope = Rope::new();
+            replace_lines(&mut rope, 0, 0, &lines);
+            state.rope = rope;
+            state.changedtick = changedtick;
+            let change = LineChange {
+                first_line: 0,
+                last_line,
+                new_last_line: lines.len(),
+                changedtick,
+            };
+            (state.rope.clone(), change)
+        };
+        self.notify(&rope, &change);
+        Ok(())
+    }
+
+    fn notify(&self, rope: &Rope, change: &LineChange) {
+        let listeners = self.state.borrow().listeners.clone();
+        for listener in listeners {
+            listener(rope, change);
+        }
+    }
+}
+
+/// 替换 [first_line, last_line) 行为 lines
+pub fn replace_lines(rope: &mut Rope, first_line: usize, last_line: usize, lines: &[String]) {
+    let count = line_count(rope);
+    let first_line = first_line.min(count);
+    let last_line = last_line.clamp(first_line, count);
+    let start = rope.line_to_char(first_line);
+    let end = rope.line_to_char(last_line);
+    rope.remove(start..end);
+    let mut text = String::with_capacity(lines.iter().map(|x| x.len() + 1).sum());
+    for line in lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+    rope.insert(start, text.as_str());
+}
+
+/// rope 镜像的行数(不含末尾换行产生的空行)
+pub fn line_count(rope: &Rope) -> usize {
+    rope.len_lines() - 1
+}
+
+/// 获取一行内容(0-based)，不含换行符
+pub fn line(rope: &Rope, index: usize) -> Option<String> {
+    if index < line_count(rope) {
+        let mut line = rope.line(index).to_string();
+        line.pop();
+        Some(line)
+    } else {
+        None
+    }
+}
+
+fn changedtick(lua: &Lua, buffer: usize) -> LuaResult<u64> {
+    lua.load(format!("vim.api.nvim_buf_get_changedtick({buffer})"))
+        .eval()
+}
+
+fn buffer_lines(lua: &Lua, buffer: usize) -> LuaResult<Vec<String>> {
+    buffer_lines_range(lua, buffer, 0, -1)
+}
+
+fn buffer_lines_range(lua: &Lua, buffer: usize, start: i64, end: i64) -> LuaResult<Vec<String>> {
+    let get_lines: LuaFunction = lua.load("vim.api.nvim_buf_get_lines").eval()?;
+    lossy_lines(get_lines.call((buffer, start, end, false))?)
+}
+
+/// buffer 中可能存在非 utf-8 内容
+fn lossy_lines(lines: Vec<LuaString>) -> LuaResult<Vec<String>> {
+    Ok(lines.iter().map(|x| x.to_string_lossy()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(list: &[&str]) -> Vec<String> {
+        list.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn replace_lines_works() {
+        let mut rope = Rope::new();
+        replace_lines(&mut rope, 0, 0, &lines(&["a", "b", "c"]));
+        assert_eq!(line_count(&rope), 3);
+        replace_lines(&mut rope, 1, 2, &lines(&["x", "y"]));
+        assert_eq!(rope.to_string(), "a\nx\ny\nc\n");
+        replace_lines(&mut rope, 3, 4, &[]);
+        assert_eq!(rope.to_string(), "a\nx\ny\n");
+        replace_lines(&mut rope, 3, 3, &lines(&["\r"]));
+        assert_eq!(line_count(&rope), 4);
+        assert_eq!(line(&rope, 3).as_deref(), Some("\r"));
+        assert_eq!(line(&rope, 4), None);
+    }
+}
@@ -0,0 +1,222 @@
+use mlua::prelude::{LuaError, LuaFunction, LuaResult, LuaTable, LuaValue};
+use mlua::{Lua, LuaSerdeExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::builtin_fn::{call_api, call_lua};
+
+/// 选项作用域，0 表示当前窗口 / buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// 全局值，对局部选项表示其全局值
+    Global,
+    Window(usize),
+    Buffer(usize),
+}
+
+/// 选项值类型
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionType {
+    Boolean,
+    Number,
+    String,
+}
+
+/// 选项自身的作用域
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionScope {
+    #[serde(rename = "global")]
+    Global,
+    #[serde(rename = "win")]
+    Window,
+    #[serde(rename = "buf")]
+    Buffer,
+}
+
+/// vim.api.nvim_get_option_info2
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct OptionInfo {
+    pub name: String,
+    #[serde(default)]
+    pub shortname: String,
+    pub r#type: OptionType,
+    pub scope: OptionScope,
+    /// 同时存在全局值和局部值
+    #[serde(default)]
+    pub global_local: bool,
+    #[serde(default)]
+    pub default: Value,
+    #[serde(default)]
+    pub was_set: bool,
+}
+
+/// 可以读写的选项值类型
+pub trait OptionValue: Serialize + DeserializeOwned {
+    const TYPE: OptionType;
+}
+
+impl OptionValue for bool {
+    const TYPE: OptionType = OptionType::Boolean;
+}
+
+impl OptionValue for i64 {
+    const TYPE: OptionType = OptionType::Number;
+}
+
+impl OptionValue for usize {
+    const TYPE: OptionType = OptionType::Number;
+}
+
+impl OptionValue for String {
+    const TYPE: OptionType = OptionType::String;
+}
+
+/// OptionSet 事件
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptionSet {
+    pub name: String,
+    pub old: Value,
+    pub new: Value,
+    /// 通过 `:setlocal` 设置
+    pub local: bool,
+    /// `set` / `setlocal` / `setglobal` / `modeline`
+    pub command: String,
+}
+
+#[derive(Serialize, Default)]
+struct ScopeOpts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    win: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    buf: Option<usize>,
+}
+
+impl From<Scope> for ScopeOpts {
+    fn from(scope: Scope) -> Self {
+        match scope {
+            Scope::Global => ScopeOpts {
+                scope: Some("global"),
+                ..Default::default()
+            },
+            Scope::Window(win) => ScopeOpts {
+                win: Some(win),
+                ..Default::default()
+            },
+            Scope::Buffer(buf) => ScopeOpts {
+                buf: Some(buf),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// vim.api.nvim_get_option_info2
+pub fn info(lua: &Lua, name: &str) -> LuaResult<OptionInfo> {
+    #[derive(Serialize)]
+    struct Opts {}
+    call_api(lua, "nvim_get_option_info2", (name, Opts {}))
+}
+
+/// vim.api.nvim_get_option_value
+/// 读取前校验选项类型与作用域
+pub fn get<T: OptionValue>(lua: &Lua, name: &str, scope: Scope) -> LuaResult<T> {
+    check(&info(lua, name)?, T::TYPE, scope)?;
+    call_api(lua, "nvim_get_option_value", (name, ScopeOpts::from(scope)))
+}
+
+/// vim.api.nvim_set_option_value
+/// 写入前校验选项类型与作用域
+pub fn set<T: OptionValue>(lua: &Lua, name: &str, scope: Scope, value: T) -> LuaResult<()> {
+    check(&info(lua, name)?, T::TYPE, scope)?;
+    call_api(
+        lua,
+        "nvim_set_option_value",
+        (name, value, ScopeOpts::from(scope)),
+    )
+}
+
+/// vim.filetype.get_option
+/// 文件类型的默认选项值，不需要存在该类型的 buffer
+pub fn filetype_option<T: OptionValue>(lua: &Lua, filetype: &str, name: &str) -> LuaResult<T> {
+    call_lua(lua, "vim.filetype.get_option", (filetype, name))
+}
+
+/// 校验选项类型与作用域
+/// 窗口 / buffer 作用域只能用于对应的局部选项，全局作用域可以用于任意选项
+pub fn check(info: &OptionInfo, r#type: OptionType, scope: Scope) -> LuaResult<()> {
+    if info.r#type != r#type {
+        return Err(LuaError::runtime(format!(
+            "option '{}' is {:?}, not {:?}",
+            info.name, info.r#type, r#type
+        )));
+    }
+    let expected = match scope {
+        Scope::Global => return Ok(()),
+        Scope::Window(_) => OptionScope::Window,
+        Scope::Buffer(_) => OptionScope::Buffer,
+    };
+    if info.scope != expected {
+        return Err(LuaError::runtime(format!(
+            "option '{}' is {:?} scoped, not {:?}",
+            info.name, info.scope, expected
+        )));
+    }
+    Ok(())
+}
+
+/// 订阅 OptionSet 事件，pattern 为选项名，`*` 表示全部选项
+/// 返回 autocmd id
+pub fn subscribe<F>(lua: &Lua, pattern: &str, mut callback: F) -> LuaResult<u32>
+where
+    F: FnMut(&Lua, OptionSet) -> LuaResult<()> + 'static,
+{
+    let on_option_set = lua.create_function_mut(move |lua, args: LuaTable| {
+        let v: LuaTable = lua.load("vim.v").eval()?;
+        let event = OptionSet {
+            name: args.get("match")?,
+            old: lua.from_value(v.get::<LuaValue>("option_old")?)?,
+            new: lua.from_value(v.get::<LuaValue>("option_new")?)?,
+            local: v.get::<String>("option_type")? == "local",
+            command: v.get("option_command")?,
+        };
+        callback(lua, event)?;
+        // 返回 true 会删除 autocmd
+        Ok(false)
+    })?;
+    let opts = lua.create_table()?;
+    opts.set("pattern", pattern)?;
+    opts.set("callback", on_option_set)?;
+    let create: LuaFunction = lua.load("vim.api.nvim_create_autocmd").eval()?;
+    create.call(("OptionSet", opts))
+}
+
+/// 取消订阅
+pub fn unsubscribe(lua: &Lua, id: u32) -> LuaResult<()> {
+    call_api(lua, "nvim_del_autocmd", (id,))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_works() {
+        let info = OptionInfo {
+            name: "shiftwidth".to_string(),
+            shortname: "sw".to_string(),
+            r#type: OptionType::Number,
+            scope: OptionScope::Buffer,
+            global_local: false,
+            default: Value::from(8),
+            was_set: false,
+        };
+        assert!(check(&info, OptionType::Number, Scope::Buffer(0)).is_ok());
+        assert!(check(&info, OptionType::Number, Scope::Global).is_ok());
+        assert!(check(&info, OptionType::Number, Scope::Window(0)).is_err());
+        assert!(check(&info, OptionType::String, Scope::Buffer(0)).is_err());
+    }
+}
@@ -0,0 +1,354 @@
+use mlua::Lua;
+use mlua::prelude::LuaResult;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::builtin_fn::call_fn;
+use crate::command::Command;
+use crate::range::Range;
+
+/// 列表项类型
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ItemType {
+    #[serde(rename = "E")]
+    Error,
+    #[serde(rename = "W")]
+    Warning,
+    #[serde(rename = "I")]
+    Info,
+    #[serde(rename = "N")]
+    Note,
+    #[serde(rename = "H")]
+    Hint,
+    #[default]
+    #[serde(rename = "", other)]
+    None,
+}
+
+/// quickfix / location list 列表项，行列从 1 开始
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QuickfixItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bufnr: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub lnum: usize,
+    #[serde(default)]
+    pub col: usize,
+    #[serde(default)]
+    pub end_lnum: usize,
+    #[serde(default)]
+    pub end_col: usize,
+    #[serde(default, rename = "type")]
+    pub r#type: ItemType,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default = "valid_default", deserialize_with = "bool_or_number")]
+    pub valid: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_data: Option<Value>,
+}
+
+/// valid 默认为 true，setqflist 会按显式的 valid 覆盖检测结果
+impl Default for QuickfixItem {
+    fn default() -> Self {
+        QuickfixItem {
+            bufnr: None,
+            filename: None,
+            lnum: 0,
+            col: 0,
+            end_lnum: 0,
+            end_col: 0,
+            r#type: ItemType::None,
+            text: String::new(),
+            valid: valid_default(),
+            user_data: None,
+        }
+    }
+}
+
+impl QuickfixItem {
+    /// 文件位置
+    pub fn new(filename: &str, lnum: usize, col: usize, text: &str) -> QuickfixItem {
+        QuickfixItem {
+            filename: Some(filename.to_string()),
+            lnum,
+            col,
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 设置起止位置，end 不包含
+    pub fn with_range(mut self, range: Range) -> Self {
+        self.lnum = range.start.row + 1;
+        self.col = range.start.col + 1;
+        self.end_lnum = range.end.row + 1;
+        self.end_col = range.end.col + 1;
+        self
+    }
+}
+
+/// 列表类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListKind {
+    Quickfix,
+    /// 窗口的 location list
+    Location(usize),
+}
+
+/// setqflist 的 action 参数
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// 创建新列表
+    New,
+    /// 追加到列表
+    Append,
+    /// 替换列表内容
+    Replace,
+    /// 释放全部列表
+    Free,
+}
+
+impl Action {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Action::New => " ",
+            Action::Append => "a",
+            Action::Replace => "r",
+            Action::Free => "f",
+        }
+    }
+}
+
+/// setqflist / getqflist 的 what 参数
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ListWhat {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nr: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idx: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<Vec<QuickfixItem>>,
+    /// 按 efm 解析的文本行
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub efm: Option<String>,
+}
+
+/// 列表信息
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct ListInfo {
+    #[serde(default)]
+    pub id: usize,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub size: usize,
+    #[serde(default)]
+    pub idx: usize,
+    /// 列表窗口，未打开时为 0
+    #[serde(default)]
+    pub winid: usize,
+    #[serde(default)]
+    pub context: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct InfoQuery {
+    id: usize,
+    title: u8,
+    size: u8,
+    idx: u8,
+    winid: u8,
+    context: u8,
+}
+
+#[derive(Serialize)]
+struct ItemsQuery {
+    id: usize,
+    items: u8,
+}
+
+#[derive(Deserialize)]
+struct ItemsResult {
+    #[serde(default)]
+    items: Vec<QuickfixItem>,
+}
+
+/// vim.fn.setqflist / vim.fn.setloclist
+pub fn set_list(lua: &Lua, kind: ListKind, action: Action, what: &ListWhat) -> LuaResult<()> {
+    let empty: [(); 0] = [];
+    let code: i32 = match kind {
+        ListKind::Quickfix => call_fn(lua, "setqflist", (empty, action.as_str(), what))?,
+        ListKind::Location(winid) => {
+            call_fn(lua, "setloclist", (winid, empty, action.as_str(), what))?
+        }
+    };
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(mlua::Error::runtime("set quickfix list failed!"))
+    }
+}
+
+/// 创建新列表，返回列表 id
+pub fn create(
+    lua: &Lua,
+    kind: ListKind,
+    title: &str,
+    items: Vec<QuickfixItem>,
+    context: Option<Value>,
+) -> LuaResult<usize> {
+    let what = ListWhat {
+        title: Some(title.to_string()),
+        items: Some(items),
+        context,
+        ..Default::default()
+    };
+    set_list(lua, kind, Action::New, &what)?;
+    Ok(info(lua, kind, 0)?.id)
+}
+
+/// 追加列表项，id 为 0 时表示当前列表
+pub fn append(lua: &Lua, kind: ListKind, id: usize, items: Vec<QuickfixItem>) -> LuaResult<()> {
+    let what = ListWhat {
+        id: Some(id),
+        items: Some(items),
+        ..Default::default()
+    };
+    set_list(lua, kind, Action::Append, &what)
+}
+
+/// vim.fn.getqflist / vim.fn.getloclist
+/// 列表信息，id 为 0 时表示当前列表
+pub fn info(lua: &Lua, kind: ListKind, id: usize) -> LuaResult<ListInfo> {
+    let query = InfoQuery {
+        id,
+        title: 1,
+        size: 1,
+        idx: 1,
+        winid: 1,
+        context: 1,
+    };
+    get_list(lua, kind, query)
+}
+
+/// 列表项，id 为 0 时表示当前列表
+pub fn items(lua: &Lua, kind: ListKind, id: usize) -> LuaResult<Vec<QuickfixItem>> {
+    let result: ItemsResult = get_list(lua, kind, ItemsQuery { id, items: 1 })?;
+    Ok(result.items)
+}
+
+fn get_list<W, R>(lua: &Lua, kind: ListKind, what: W) -> LuaResult<R>
+where
+    W: Serialize,
+    R: serde::de::DeserializeOwned,
+{
+    match kind {
+        ListKind::Quickfix => call_fn(lua, "getqflist", (what,)),
+        ListKind::Location(winid) => call_fn(lua, "getloclist", (winid, what)),
+    }
+}
+
+/// :copen / :lopen
+pub fn open(lua: &Lua, kind: ListKind, height: Option<usize>) -> LuaResult<()> {
+    let command = list_cmd(kind, "open");
+    let command = match height {
+        Some(height) => command.count(height),
+        None => command,
+    };
+    exec(lua, kind, &command)
+}
+
+/// :cclose / :lclose
+pub fn close(lua: &Lua, kind: ListKind) -> LuaResult<()> {
+    exec(lua, kind, &list_cmd(kind, "close"))
+}
+
+/// :cc N / :ll N，跳转到第 nr 项(从 1 开始)
+pub fn jump(lua: &Lua, kind: ListKind, nr: usize) -> LuaResult<()> {
+    let name = match kind {
+        ListKind::Quickfix => "c",
+        ListKind::Location(_) => "l",
+    };
+    exec(lua, kind, &list_cmd(kind, name).count(nr))
+}
+
+/// :cnext / :lnext
+pub fn next(lua: &Lua, kind: ListKind) -> LuaResult<()> {
+    exec(lua, kind, &list_cmd(kind, "next"))
+}
+
+/// :cprevious / :lprevious
+pub fn prev(lua: &Lua, kind: ListKind) -> LuaResult<()> {
+    exec(lua, kind, &list_cmd(kind, "previous"))
+}
+
+/// quickfix 命令加 `c` 前缀，location list 命令加 `l` 前缀
+fn list_cmd(kind: ListKind, name: &str) -> Command {
+    match kind {
+        ListKind::Quickfix => Command::new(format!("c{name}").as_str()),
+        ListKind::Location(_) => Command::new(format!("l{name}").as_str()),
+    }
+}
+
+/// location list 命令在对应窗口中执行
+fn exec(lua: &Lua, kind: ListKind, command: &Command) -> LuaResult<()> {
+    match kind {
+        ListKind::Quickfix => command.exec(lua),
+        ListKind::Location(winid) => command.exec_in(lua, winid),
+    }
+}
+
+fn valid_default() -> bool {
+    true
+}
+
+/// getqflist 返回的 valid 为 0/1
+fn bool_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrNumber {
+        Bool(bool),
+        Number(i64),
+    }
+    Ok(match BoolOrNumber::deserialize(deserializer)? {
+        BoolOrNumber::Bool(value) => value,
+        BoolOrNumber::Number(value) => value != 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_deserialize_works() {
+        let item: QuickfixItem = serde_json::from_str(
+            r#"{"bufnr": 1, "lnum": 3, "col": 5, "type": "W", "valid": 0, "text": "unused"}"#,
+        )
+        .unwrap();
+        assert_eq!(item.r#type, ItemType::Warning);
+        assert!(!item.valid);
+        let item: QuickfixItem = serde_json::from_str(r#"{"type": "x"}"#).unwrap();
+        assert_eq!(item.r#type, ItemType::None);
+        assert!(item.valid);
+    }
+
+    #[test]
+    fn item_serialize_valid() {
+        let item = QuickfixItem::new("src/lib.rs", 3, 1, "a");
+        let value = serde_json::to_value(&item).unwrap();
+        assert_eq!(value["valid"], serde_json::json!(true));
+        assert!(QuickfixItem::default().valid);
+    }
+}
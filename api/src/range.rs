@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use crate::func::Position;
+use crate::quickfix::QuickfixItem;
+
+/// 位置，行列从 0 开始，列为字节偏移
+/// 序列化为 LSP Position 形状 `{ line, character }`
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct Point {
+    #[serde(rename = "line")]
+    pub row: usize,
+    #[serde(rename = "character")]
+    pub col: usize,
+}
+
+/// 范围 [start, end)，序列化为 LSP Range 形状 `{ start, end }`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Range {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl Point {
+    pub fn new(row: usize, col: usize) -> Point {
+        Point { row, col }
+    }
+
+    /// 从当前位置写入 text 后的位置
+    pub fn advance(self, text: &str) -> Point {
+        match text.rfind('\n') {
+            Some(index) => Point::new(
+                self.row + text.matches('\n').count(),
+                text.len() - index - 1,
+            ),
+            None => Point::new(self.row, self.col + text.len()),
+        }
+    }
+}
+
+impl Range {
+    pub fn new(start: Point, end: Point) -> Range {
+        Range { start, end }
+    }
+
+    /// 空范围
+    pub fn point(point: Point) -> Range {
+        Range {
+            start: point,
+            end: point,
+        }
+    }
+
+    /// 整行范围 [start_row, end_row)
+    pub fn lines(start_row: usize, end_row: usize) -> Range {
+        Range {
+            start: Point::new(start_row, 0),
+            end: Point::new(end_row, 0),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.start <= point && point < self.end
+    }
+}
+
+/// vim.fn.getpos 的行列从 1 开始
+impl From<&Position> for Point {
+    fn from(position: &Position) -> Self {
+        Point {
+            row: position.row.saturating_sub(1),
+            col: position.col.saturating_sub(1),
+        }
+    }
+}
+
+/// quickfix 行列从 1 开始，end_col 不包含，未设置结束位置时为空范围
+impl From<&QuickfixItem> for Range {
+    fn from(item: &QuickfixItem) -> Self {
+        let start = Point::new(item.lnum.saturating_sub(1), item.col.saturating_sub(1));
+        let end = if item.end_lnum == 0 {
+            start
+        } else {
+            Point::new(item.end_lnum - 1, item.end_col.saturating_sub(1))
+        };
+        Range { start, end }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quickfix_range_works() {
+        let item = QuickfixItem {
+            lnum: 2,
+            col: 3,
+            end_lnum: 2,
+            end_col: 7,
+            ..Default::default()
+        };
+        let range = Range::from(&item);
+        assert_eq!(range, Range::new(Point::new(1, 2), Point::new(1, 6)));
+        assert!(range.contains(Point::new(1, 5)));
+        assert!(!range.contains(Point::new(1, 6)));
+        assert_eq!(item.clone().with_range(range), item);
+    }
+}
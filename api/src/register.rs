@@ -0,0 +1,227 @@
+use mlua::prelude::{LuaFunction, LuaResult, LuaTable, LuaValue};
+use mlua::{Lua, LuaSerdeExt};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::builtin_fn::{call_api, call_fn};
+use crate::range::Range;
+
+/// 无名寄存器
+pub const UNNAMED: char = '"';
+/// 系统剪贴板
+pub const CLIPBOARD: char = '+';
+/// 主选区 (X11 primary selection)
+pub const SELECTION: char = '*';
+
+/// 寄存器类型
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RegType {
+    /// `v`
+    #[default]
+    Charwise,
+    /// `V`
+    Linewise,
+    /// `<C-V>{width}`，width 为显示宽度
+    Blockwise(usize),
+}
+
+impl RegType {
+    /// 解析 getregtype() 的返回值
+    pub fn parse(value: &str) -> Option<RegType> {
+        match value {
+            "v" | "c" => Some(RegType::Charwise),
+            "V" | "l" => Some(RegType::Linewise),
+            _ => {
+                let width = value
+                    .strip_prefix('\x16')
+                    .or_else(|| value.strip_prefix('b'))?;
+                Some(RegType::Blockwise(width.parse().unwrap_or_default()))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for RegType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegType::Charwise => write!(f, "v"),
+            RegType::Linewise => write!(f, "V"),
+            RegType::Blockwise(width) => write!(f, "\x16{width}"),
+        }
+    }
+}
+
+impl Serialize for RegType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for RegType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        RegType::parse(value.as_str())
+            .ok_or_else(|| D::Error::custom(format!("invalid register type: {value:?}")))
+    }
+}
+
+/// 寄存器内容
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Register {
+    #[serde(rename = "regcontents")]
+    pub contents: Vec<String>,
+    pub regtype: RegType,
+}
+
+impl Register {
+    pub fn new(contents: Vec<String>, regtype: RegType) -> Register {
+        Register { contents, regtype }
+    }
+
+    /// 按换行拆分的字符内容
+    pub fn charwise(text: &str) -> Register {
+        Self::new(
+            text.split('\n').map(|x| x.to_string()).collect(),
+            RegType::Charwise,
+        )
+    }
+
+    /// 文本内容，整行寄存器以换行结尾
+    pub fn text(&self) -> String {
+        let text = self.contents.join("\n");
+        match self.regtype {
+            RegType::Linewise => text + "\n",
+            _ => text,
+        }
+    }
+}
+
+/// TextYankPost 事件，对应 v:event
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct YankEvent {
+    /// `y` / `d` / `c`
+    pub operator: String,
+    pub regcontents: Vec<String>,
+    /// 空字符串表示无名寄存器
+    pub regname: String,
+    pub regtype: RegType,
+    pub visual: bool,
+    #[serde(default)]
+    pub inclusive: bool,
+}
+
+impl YankEvent {
+    pub fn register(&self) -> Register {
+        Register::new(self.regcontents.clone(), self.regtype)
+    }
+}
+
+/// vim.fn.getreginfo
+/// 寄存器为空时返回 None
+pub fn get(lua: &Lua, name: char) -> LuaResult<Option<Register>> {
+    #[derive(Deserialize)]
+    struct RegInfo {
+        #[serde(default)]
+        regcontents: Vec<String>,
+        #[serde(default)]
+        regtype: Option<RegType>,
+    }
+    let info: RegInfo = call_fn(lua, "getreginfo", (name.to_string(),))?;
+    Ok(info
+        .regtype
+        .map(|regtype| Register::new(info.regcontents, regtype)))
+}
+
+/// vim.fn.setreg
+pub fn set(lua: &Lua, name: char, register: &Register) -> LuaResult<()> {
+    let _: i32 = call_fn(lua, "setreg", (name.to_string(), register))?;
+    Ok(())
+}
+
+/// 系统剪贴板内容
+pub fn clipboard(lua: &Lua) -> LuaResult<Option<Register>> {
+    get(lua, CLIPBOARD)
+}
+
+/// 写入系统剪贴板
+pub fn set_clipboard(lua: &Lua, text: &str) -> LuaResult<()> {
+    set(lua, CLIPBOARD, &Register::charwise(text))
+}
+
+/// 复制 buffer 中 range 的文本到寄存器
+pub fn yank(lua: &Lua, name: char, buffer: usize, range: Range) -> LuaResult<Register> {
+    let contents = crate::buffer::get_text(lua, buffer, range)?;
+    let register = Register::new(contents, RegType::Charwise);
+    set(lua, name, &register)?;
+    Ok(register)
+}
+
+/// 复制 buffer 中 [start_row, end_row) 行到寄存器
+pub fn yank_lines(
+    lua: &Lua,
+    name: char,
+    buffer: usize,
+    start_row: usize,
+    end_row: usize,
+) -> LuaResult<Register> {
+    let contents: Vec<String> = call_api(
+        lua,
+        "nvim_buf_get_lines",
+        (buffer, start_row, end_row, false),
+    )?;
+    let register = Register::new(contents, RegType::Linewise);
+    set(lua, name, &register)?;
+    Ok(register)
+}
+
+/// vim.api.nvim_put
+/// 在当前窗口光标处粘贴，after 为 true 时粘贴到光标之后，follow 为 true 时光标移动到粘贴内容末尾
+pub fn put(lua: &Lua, register: &Register, after: bool, follow: bool) -> LuaResult<()> {
+    call_api(
+        lua,
+        "nvim_put",
+        (&register.contents, register.regtype, after, follow),
+    )
+}
+
+/// 粘贴寄存器 name 的内容，寄存器为空时不做任何操作
+pub fn put_register(lua: &Lua, name: char, after: bool, follow: bool) -> LuaResult<()> {
+    match get(lua, name)? {
+        Some(register) => put(lua, &register, after, follow),
+        None => Ok(()),
+    }
+}
+
+/// 订阅 TextYankPost 事件，返回 autocmd id
+pub fn subscribe<F>(lua: &Lua, mut callback: F) -> LuaResult<u32>
+where
+    F: FnMut(&Lua, YankEvent) -> LuaResult<()> + 'static,
+{
+    let on_yank = lua.create_function_mut(move |lua, _: LuaValue| {
+        let v: LuaTable = lua.load("vim.v").eval()?;
+        let event = lua.from_value(v.get::<LuaValue>("event")?)?;
+        callback(lua, event)?;
+        Ok(false)
+    })?;
+    let opts = lua.create_table()?;
+    opts.set("callback", on_yank)?;
+    let create: LuaFunction = lua.load("vim.api.nvim_create_autocmd").eval()?;
+    create.call(("TextYankPost", opts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regtype_works() {
+        assert_eq!(RegType::parse("v"), Some(RegType::Charwise));
+        assert_eq!(RegType::parse("V"), Some(RegType::Linewise));
+        assert_eq!(RegType::parse("\x1612"), Some(RegType::Blockwise(12)));
+        assert_eq!(RegType::parse(""), None);
+        assert_eq!(RegType::Blockwise(3).to_string(), "\x163");
+        let register = Register::new(vec!["a".to_string(), "b".to_string()], RegType::Linewise);
+        assert_eq!(register.text(), "a\nb\n");
+        assert_eq!(Register::charwise("a\nb").contents.len(), 2);
+    }
+}
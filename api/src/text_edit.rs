@@ -0,0 +1,198 @@
+use mlua::Lua;
+use mlua::prelude::{LuaError, LuaResult};
+use serde::{Deserialize, Serialize};
+
+use crate::builtin_fn::call_api;
+use crate::range::{Point, Range};
+
+/// LSP TextEdit 形状的文本编辑，range 为 [start, end)，列为字节偏移
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range,
+    #[serde(rename = "newText")]
+    pub new_text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range, new_text: &str) -> TextEdit {
+        TextEdit {
+            range,
+            new_text: new_text.to_string(),
+        }
+    }
+
+    /// 在 point 处插入
+    pub fn insert(point: Point, text: &str) -> TextEdit {
+        Self::new(Range::point(point), text)
+    }
+
+    /// 删除 range
+    pub fn delete(range: Range) -> TextEdit {
+        Self::new(range, "")
+    }
+}
+
+/// 批量应用编辑
+/// 编辑之间不能重叠，只修改实际变化的部分以保留 mark / extmark，
+/// 全部编辑在同一次调用中完成，撤销时为一个整体，当前窗口光标随编辑偏移
+pub fn apply(lua: &Lua, buffer: usize, edits: Vec<TextEdit>) -> LuaResult<()> {
+    let edits = validate(edits)?;
+    let mut minimal = Vec::with_capacity(edits.len());
+    for edit in edits {
+        let old_text = get_text(lua, buffer, edit.range)?;
+        if let Some(edit) = minimize(&edit, old_text.as_str()) {
+            minimal.push(edit);
+        }
+    }
+    if minimal.is_empty() {
+        return Ok(());
+    }
+    let cursor = current_cursor(lua, buffer)?;
+    for edit in minimal.iter().rev() {
+        let lines = edit.new_text.split('\n').collect::<Vec<_>>();
+        let Range { start, end } = edit.range;
+        let _: () = call_api(
+            lua,
+            "nvim_buf_set_text",
+            (buffer, start.row, start.col, end.row, end.col, lines),
+        )?;
+    }
+    if let Some(cursor) = cursor {
+        let cursor = adjust_point(cursor, &minimal);
+        let _: () = call_api(
+            lua,
+            "nvim_win_set_cursor",
+            (0, (cursor.row + 1, cursor.col)),
+        )?;
+    }
+    Ok(())
+}
+
+/// 按起始位置排序并检查重叠，同一位置的插入保持原有顺序
+pub fn validate(mut edits: Vec<TextEdit>) -> LuaResult<Vec<TextEdit>> {
+    if let Some(edit) = edits.iter().find(|x| x.range.start > x.range.end) {
+        return Err(LuaError::runtime(format!(
+            "text edit range is reversed: {:?}",
+            edit.range
+        )));
+    }
+    edits.sort_by_key(|x| x.range.start);
+    for pair in edits.windows(2) {
+        if pair[0].range.end > pair[1].range.start {
+            return Err(LuaError::runtime(format!(
+                "text edits overlap: {:?} and {:?}",
+                pair[0].range, pair[1].range
+            )));
+        }
+    }
+    Ok(edits)
+}
+
+/// 去掉新旧文本的公共前后缀，没有变化时返回 None
+pub fn minimize(edit: &TextEdit, old_text: &str) -> Option<TextEdit> {
+    let new_text = edit.new_text.as_str();
+    if old_text == new_text {
+        return None;
+    }
+    let prefix = common_prefix(old_text, new_text);
+    let suffix = common_suffix(&old_text[prefix..], &new_text[prefix..]);
+    let start = edit.range.start.advance(&old_text[..prefix]);
+    let end = edit.range.start.advance(&old_text[..old_text.len() - suffix]);
+    Some(TextEdit::new(
+        Range::new(start, end),
+        &new_text[prefix..new_text.len() - suffix],
+    ))
+}
+
+/// 按已排序的编辑调整位置
+pub fn adjust_point(point: Point, edits: &[TextEdit]) -> Point {
+    edits.iter().rev().fold(point, |point, edit| {
+        let Range { start, end } = edit.range;
+        let new_end = start.advance(edit.new_text.as_str());
+        if point < start {
+            point
+        } else if point >= end {
+            if point.row == end.row {
+                Point::new(new_end.row, new_end.col + (point.col - end.col))
+            } else {
+                Point::new(point.row + new_end.row - end.row, point.col)
+            }
+        } else {
+            point.min(new_end)
+        }
+    })
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map(|((index, _), _)| index)
+        .unwrap_or(a.len().min(b.len()))
+}
+
+fn common_suffix(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x.len_utf8())
+        .sum()
+}
+
+fn get_text(lua: &Lua, buffer: usize, range: Range) -> LuaResult<String> {
+    Ok(crate::buffer::get_text(lua, buffer, range)?.join("\n"))
+}
+
+/// 当前窗口显示该 buffer 时返回光标位置
+fn current_cursor(lua: &Lua, buffer: usize) -> LuaResult<Option<Point>> {
+    let current: usize = call_api(lua, "nvim_get_current_buf", ())?;
+    if buffer != 0 && buffer != current {
+        return Ok(None);
+    }
+    let (row, col): (usize, usize) = call_api(lua, "nvim_win_get_cursor", (0,))?;
+    Ok(Some(Point::new(row - 1, col)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_works() {
+        let edits = vec![
+            TextEdit::insert(Point::new(2, 0), "b"),
+            TextEdit::insert(Point::new(1, 0), "a"),
+        ];
+        let edits = validate(edits).unwrap();
+        assert_eq!(edits[0].new_text, "a");
+        let overlap = vec![
+            TextEdit::delete(Range::new(Point::new(0, 0), Point::new(0, 4))),
+            TextEdit::delete(Range::new(Point::new(0, 2), Point::new(0, 6))),
+        ];
+        assert!(validate(overlap).is_err());
+    }
+
+    #[test]
+    fn minimize_works() {
+        let range = Range::new(Point::new(3, 0), Point::new(3, 10));
+        let edit = TextEdit::new(range, "    // let a;");
+        let edit = minimize(&edit, "    let a;").unwrap();
+        assert_eq!(edit.range, Range::point(Point::new(3, 4)));
+        assert_eq!(edit.new_text, "// ");
+        let edit = TextEdit::new(Range::new(Point::new(0, 0), Point::new(1, 1)), "a\nb");
+        assert_eq!(minimize(&edit, "a\nb"), None);
+    }
+
+    #[test]
+    fn adjust_point_works() {
+        let edits = vec![
+            TextEdit::insert(Point::new(0, 4), "// "),
+            TextEdit::delete(Range::new(Point::new(1, 0), Point::new(2, 0))),
+        ];
+        assert_eq!(adjust_point(Point::new(0, 6), &edits), Point::new(0, 9));
+        assert_eq!(adjust_point(Point::new(0, 2), &edits), Point::new(0, 2));
+        assert_eq!(adjust_point(Point::new(3, 1), &edits), Point::new(2, 1));
+        assert_eq!(adjust_point(Point::new(1, 3), &edits), Point::new(1, 0));
+    }
+}
@@ -0,0 +1,305 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use mlua::prelude::*;
+
+/// 按分组(插件名)登记的定时器，插件重新初始化时统一取消
+#[derive(Default)]
+struct TimerRegistry {
+    next_id: u64,
+    groups: HashMap<String, Vec<Timer>>,
+}
+
+/// vim.uv 定时器
+#[derive(Clone)]
+pub struct Timer {
+    id: u64,
+    group: Option<String>,
+    handle: LuaAnyUserData,
+}
+
+impl Timer {
+    /// vim.uv.new_timer
+    pub fn new(lua: &Lua) -> LuaResult<Timer> {
+        Self::create(lua, None)
+    }
+
+    /// 创建定时器并登记到分组，`clear_group` 时取消
+    pub fn with_group(lua: &Lua, group: &str) -> LuaResult<Timer> {
+        Self::create(lua, Some(group))
+    }
+
+    fn create(lua: &Lua, group: Option<&str>) -> LuaResult<Timer> {
+        let handle: LuaAnyUserData = lua.load("vim.uv.new_timer()").eval()?;
+        if lua.app_data_ref::<TimerRegistry>().is_none() {
+            lua.set_app_data(TimerRegistry::default());
+        }
+        let mut registry = lua
+            .app_data_mut::<TimerRegistry>()
+            .ok_or_else(|| LuaError::runtime("timer registry not found!"))?;
+        registry.next_id += 1;
+        let timer = Timer {
+            id: registry.next_id,
+            group: group.map(|x| x.to_string()),
+            handle,
+        };
+        if let Some(group) = group {
+            registry
+                .groups
+                .entry(group.to_string())
+                .or_default()
+                .push(timer.clone());
+        }
+        Ok(timer)
+    }
+
+    /// 启动定时器，timeout 毫秒后首次触发，repeat 不为 0 时重复触发
+    /// 回调通过 vim.schedule_wrap 在主循环中执行，可以调用 nvim api
+    pub fn start<F>(&self, lua: &Lua, timeout: u64, repeat: u64, callback: F) -> LuaResult<()>
+    where
+        F: FnMut(&Lua) -> LuaResult<()> + 'static,
+    {
+        let callback = schedule_wrap(lua, callback)?;
+        self.start_function(timeout, repeat, &callback)
+    }
+
+    fn start_function(&self, timeout: u64, repeat: u64, callback: &LuaFunction) -> LuaResult<()> {
+        self.handle
+            .call_method("start", (timeout, repeat, callback))
+    }
+
+    /// 停止定时器，可以再次 start
+    pub fn stop(&self) -> LuaResult<()> {
+        if self.is_closing()? {
+            return Ok(());
+        }
+        self.handle.call_method("stop", ())
+    }
+
+    /// 关闭定时器，释放 libuv 句柄
+    pub fn close(&self, lua: &Lua) -> LuaResult<()> {
+        if let Some(group) = self.group.as_ref()
+            && let Some(mut registry) = lua.app_data_mut::<TimerRegistry>()
+            && let Some(timers) = registry.groups.get_mut(group)
+        {
+            timers.retain(|x| x.id != self.id);
+        }
+        self.close_handle()
+    }
+
+    fn close_handle(&self) -> LuaResult<()> {
+        if self.is_closing()? {
+            return Ok(());
+        }
+        self.handle.call_method::<()>("stop", ())?;
+        self.handle.call_method("close", ())
+    }
+
+    /// 是否正在计时
+    pub fn is_active(&self) -> LuaResult<bool> {
+        if self.is_closing()? {
+            return Ok(false);
+        }
+        self.handle.call_method("is_active", ())
+    }
+
+    fn is_closing(&self) -> LuaResult<bool> {
+        self.handle.call_method("is_closing", ())
+    }
+}
+
+/// 取消并关闭分组内的全部定时器
+pub fn clear_group(lua: &Lua, group: &str) -> LuaResult<()> {
+    let timers = match lua.app_data_mut::<TimerRegistry>() {
+        Some(mut registry) => registry.groups.remove(group).unwrap_or_default(),
+        None => return Ok(()),
+    };
+    for timer in timers {
+        timer.close_handle()?;
+    }
+    Ok(())
+}
+
+/// 延迟 timeout 毫秒执行一次，执行后关闭定时器
+pub fn set_timeout<F>(lua: &Lua, timeout: u64, callback: F) -> LuaResult<Timer>
+where
+    F: FnOnce(&Lua) -> LuaResult<()> + 'static,
+{
+    let timer = Timer::new(lua)?;
+    let close = timer.clone();
+    let mut callback = Some(callback);
+    timer.start(lua, timeout, 0, move |lua| {
+        close.close(lua)?;
+        match callback.take() {
+            Some(callback) => callback(lua),
+            None => Ok(()),
+        }
+    })?;
+    Ok(timer)
+}
+
+/// 每隔 interval 毫秒执行一次
+pub fn set_interval<F>(lua: &Lua, interval: u64, callback: F) -> LuaResult<Timer>
+where
+    F: FnMut(&Lua) -> LuaResult<()> + 'static,
+{
+    let timer = Timer::new(lua)?;
+    timer.start(lua, interval, interval, callback)?;
+    Ok(timer)
+}
+
+type Callback<T> = Rc<RefCell<dyn FnMut(&Lua, T) -> LuaResult<()>>>;
+
+/// 防抖：最后一次调用 delay 毫秒后以最新参数执行
+pub struct Debounce<T> {
+    timer: Timer,
+    delay: u64,
+    pending: Rc<RefCell<Option<T>>>,
+    callback: LuaFunction,
+}
+
+impl<T: 'static> Debounce<T> {
+    pub fn new<F>(lua: &Lua, delay: u64, callback: F) -> LuaResult<Self>
+    where
+        F: FnMut(&Lua, T) -> LuaResult<()> + 'static,
+    {
+        Self::create(lua, Timer::new(lua)?, delay, callback)
+    }
+
+    /// 定时器登记到分组
+    pub fn with_group<F>(lua: &Lua, group: &str, delay: u64, callback: F) -> LuaResult<Self>
+    where
+        F: FnMut(&Lua, T) -> LuaResult<()> + 'static,
+    {
+        Self::create(lua, Timer::with_group(lua, group)?, delay, callback)
+    }
+
+    fn create<F>(lua: &Lua, timer: Timer, delay: u64, callback: F) -> LuaResult<Self>
+    where
+        F: FnMut(&Lua, T) -> LuaResult<()> + 'static,
+    {
+        let pending = Rc::new(RefCell::new(None));
+        let callback: Callback<T> = Rc::new(RefCell::new(callback));
+        let fire = pending.clone();
+        let callback = schedule_wrap(lua, move |lua| {
+            let value = fire.borrow_mut().take();
+            match value {
+                Some(value) => (callback.borrow_mut())(lua, value),
+                None => Ok(()),
+            }
+        })?;
+        Ok(Debounce {
+            timer,
+            delay,
+            pending,
+            callback,
+        })
+    }
+
+    /// 记录参数并重新计时
+    pub fn call(&self, value: T) -> LuaResult<()> {
+        self.pending.replace(Some(value));
+        self.timer.stop()?;
+        self.timer.start_function(self.delay, 0, &self.callback)
+    }
+
+    /// 取消尚未执行的调用
+    pub fn cancel(&self) -> LuaResult<()> {
+        self.pending.replace(None);
+        self.timer.stop()
+    }
+
+    /// 关闭定时器
+    pub fn close(&self, lua: &Lua) -> LuaResult<()> {
+        self.pending.replace(None);
+        self.timer.close(lua)
+    }
+}
+
+/// 节流：立即执行，interval 毫秒内的后续调用合并为窗口结束时的一次执行
+pub struct Throttle<T> {
+    timer: Timer,
+    interval: u64,
+    pending: Rc<RefCell<Option<T>>>,
+    callback: Callback<T>,
+    trailing: LuaFunction,
+}
+
+impl<T: 'static> Throttle<T> {
+    pub fn new<F>(lua: &Lua, interval: u64, callback: F) -> LuaResult<Self>
+    where
+        F: FnMut(&Lua, T) -> LuaResult<()> + 'static,
+    {
+        Self::create(lua, Timer::new(lua)?, interval, callback)
+    }
+
+    /// 定时器登记到分组
+    pub fn with_group<F>(lua: &Lua, group: &str, interval: u64, callback: F) -> LuaResult<Self>
+    where
+        F: FnMut(&Lua, T) -> LuaResult<()> + 'static,
+    {
+        Self::create(lua, Timer::with_group(lua, group)?, interval, callback)
+    }
+
+    fn create<F>(lua: &Lua, timer: Timer, interval: u64, callback: F) -> LuaResult<Self>
+    where
+        F: FnMut(&Lua, T) -> LuaResult<()> + 'static,
+    {
+        let pending: Rc<RefCell<Option<T>>> = Rc::new(RefCell::new(None));
+        let callback: Callback<T> = Rc::new(RefCell::new(callback));
+        let fire = pending.clone();
+        let fire_callback = callback.clone();
+        let fire_timer = timer.clone();
+        let trailing = schedule_wrap(lua, move |lua| {
+            let value = fire.borrow_mut().take();
+            match value {
+                Some(value) => (fire_callback.borrow_mut())(lua, value),
+                // 窗口内没有新的调用，结束节流
+                None => fire_timer.stop(),
+            }
+        })?;
+        Ok(Throttle {
+            timer,
+            interval,
+            pending,
+            callback,
+            trailing,
+        })
+    }
+
+    /// 窗口外立即执行，窗口内只保留最新参数
+    pub fn call(&self, lua: &Lua, value: T) -> LuaResult<()> {
+        if self.timer.is_active()? {
+            self.pending.replace(Some(value));
+            Ok(())
+        } else {
+            self.timer
+                .start_function(self.interval, self.interval, &self.trailing)?;
+            (self.callback.borrow_mut())(lua, value)
+        }
+    }
+
+    /// 取消尚未执行的调用
+    pub fn cancel(&self) -> LuaResult<()> {
+        self.pending.replace(None);
+        self.timer.stop()
+    }
+
+    /// 关闭定时器
+    pub fn close(&self, lua: &Lua) -> LuaResult<()> {
+        self.pending.replace(None);
+        self.timer.close(lua)
+    }
+}
+
+/// vim.schedule_wrap
+/// libuv 回调处于 fast event 中，包装后才能调用 nvim api
+fn schedule_wrap<F>(lua: &Lua, mut callback: F) -> LuaResult<LuaFunction>
+where
+    F: FnMut(&Lua) -> LuaResult<()> + 'static,
+{
+    let callback = lua.create_function_mut(move |lua, ()| callback(lua))?;
+    let schedule_wrap: LuaFunction = lua.load("vim.schedule_wrap").eval()?;
+    schedule_wrap.call(callback)
+}
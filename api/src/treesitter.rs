@@ -0,0 +1,211 @@
+use mlua::prelude::{LuaFunction, LuaResult, LuaValue};
+use mlua::{Lua, LuaSerdeExt};
+use serde::{Deserialize, Serialize};
+
+use crate::builtin_fn::call_lua;
+use crate::error::VimError;
+use crate::range::{Point, Range};
+
+/// 语法节点
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    /// 节点类型，例如 `function_item`
+    pub kind: String,
+    /// 节点所属语言
+    pub lang: String,
+    pub named: bool,
+    pub range: Range,
+}
+
+/// 查询捕获
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Capture {
+    /// 捕获名，不含 `@`
+    pub name: String,
+    pub node: Node,
+}
+
+/// lua 侧公共函数，节点转换为 Node 形状的 table
+const PRELUDE: &str = r#"
+local function parser_of(buf, start_row, end_row)
+  local ok, parser = pcall(vim.treesitter.get_parser, buf)
+  if not ok or not parser then
+    return nil
+  end
+  parser:parse({ start_row, end_row })
+  return parser
+end
+local function to_node(node, lang)
+  local sr, sc, er, ec = node:range()
+  return {
+    kind = node:type(),
+    lang = lang,
+    named = node:named(),
+    range = { start = { line = sr, character = sc }, ["end"] = { line = er, character = ec } },
+  }
+end
+"#;
+
+const HAS_PARSER: &str = r#"
+local buf = ...
+local ok, parser = pcall(vim.treesitter.get_parser, buf)
+return ok and parser ~= nil
+"#;
+
+const LANGUAGE_AT: &str = r#"
+local buf, row, col = ...
+local parser = parser_of(buf, row, row + 1)
+if parser then
+  return parser:language_for_range({ row, col, row, col }):lang()
+end
+"#;
+
+const LANGUAGES_AT: &str = r#"
+local buf, row, col = ...
+local parser = parser_of(buf, row, row + 1)
+local langs = {}
+if parser then
+  local tree = parser:language_for_range({ row, col, row, col })
+  while tree do
+    langs[#langs + 1] = tree:lang()
+    tree = tree:parent()
+  end
+end
+return langs
+"#;
+
+const ANCESTORS: &str = r#"
+local buf, row, col, ignore_injections = ...
+local parser = parser_of(buf, row, row + 1)
+if not parser then
+  return {}
+end
+local tree = ignore_injections and parser or parser:language_for_range({ row, col, row, col })
+local node = tree:named_node_for_range({ row, col, row, col }, { ignore_injections = ignore_injections })
+local nodes = {}
+while node do
+  nodes[#nodes + 1] = to_node(node, tree:lang())
+  node = node:parent()
+end
+return nodes
+"#;
+
+const QUERY: &str = r#"
+local buf, lang, source, start_row, end_row = ...
+local parser = parser_of(buf, start_row, end_row)
+if not parser then
+  return {}
+end
+lang = lang or parser:lang()
+local query = vim.treesitter.query.parse(lang, source)
+local captures = {}
+parser:for_each_tree(function(tree, ltree)
+  if ltree:lang() == lang then
+    for id, node in query:iter_captures(tree:root(), buf, start_row, end_row) do
+      captures[#captures + 1] = { name = query.captures[id], node = to_node(node, lang) }
+    end
+  end
+end)
+return captures
+"#;
+
+/// 是否存在 treesitter parser
+pub fn has_parser(lua: &Lua, buffer: usize) -> LuaResult<bool> {
+    call(lua, "has_parser", HAS_PARSER, buffer)
+}
+
+/// 位置所在的语言，包含注入语言，没有 parser 时返回 None
+pub fn language_at(lua: &Lua, buffer: usize, point: Point) -> LuaResult<Option<String>> {
+    call(
+        lua,
+        "language_at",
+        LANGUAGE_AT,
+        (buffer, point.row, point.col),
+    )
+}
+
+/// 位置所在的语言及其外层语言，由内向外，没有 parser 时为空
+pub fn languages_at(lua: &Lua, buffer: usize, point: Point) -> LuaResult<Vec<String>> {
+    call(
+        lua,
+        "languages_at",
+        LANGUAGES_AT,
+        (buffer, point.row, point.col),
+    )
+}
+
+/// vim.treesitter.language.get_filetypes
+/// 使用该语言 parser 的文件类型，例如 `bash` 对应 `sh`
+pub fn filetypes(lua: &Lua, lang: &str) -> LuaResult<Vec<String>> {
+    call_lua(lua, "vim.treesitter.language.get_filetypes", (lang,))
+}
+
+/// 位置上最小的具名节点
+pub fn node_at(
+    lua: &Lua,
+    buffer: usize,
+    point: Point,
+    ignore_injections: bool,
+) -> LuaResult<Option<Node>> {
+    Ok(ancestors(lua, buffer, point, ignore_injections)?
+        .into_iter()
+        .next())
+}
+
+/// 位置上的具名节点及其全部祖先，由内向外
+pub fn ancestors(
+    lua: &Lua,
+    buffer: usize,
+    point: Point,
+    ignore_injections: bool,
+) -> LuaResult<Vec<Node>> {
+    call(
+        lua,
+        "ancestors",
+        ANCESTORS,
+        (buffer, point.row, point.col, ignore_injections),
+    )
+}
+
+/// 在行范围 [start_row, end_row) 内执行查询
+/// lang 为 None 时使用 buffer 的主语言，否则同时查询该语言的注入部分
+pub fn query(
+    lua: &Lua,
+    buffer: usize,
+    lang: Option<&str>,
+    source: &str,
+    start_row: usize,
+    end_row: usize,
+) -> LuaResult<Vec<Capture>> {
+    call(
+        lua,
+        "query",
+        QUERY,
+        (buffer, lang, source, start_row, end_row),
+    )
+}
+
+fn call<A, R>(lua: &Lua, name: &str, source: &str, args: A) -> LuaResult<R>
+where
+    A: mlua::IntoLuaMulti,
+    R: serde::de::DeserializeOwned,
+{
+    let result: LuaValue = helper(lua, name, source)?
+        .call(args)
+        .map_err(|err| VimError::from_lua(name, &err))?;
+    lua.from_value(result)
+}
+
+/// 编译后的 lua 函数缓存在 registry 中
+fn helper(lua: &Lua, name: &str, source: &str) -> LuaResult<LuaFunction> {
+    let key = format!("api.treesitter.{name}");
+    if let Some(func) = lua.named_registry_value::<Option<LuaFunction>>(key.as_str())? {
+        return Ok(func);
+    }
+    let func = lua
+        .load(format!("{PRELUDE}{source}"))
+        .set_name(key.as_str())
+        .into_function()?;
+    lua.set_named_registry_value(key.as_str(), &func)?;
+    Ok(func)
+}
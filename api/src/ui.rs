@@ -0,0 +1,187 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use mlua::prelude::{LuaError, LuaFunction, LuaResult, LuaThread, LuaThreadStatus};
+use mlua::{Lua, LuaSerdeExt};
+use serde::Serialize;
+
+use crate::builtin_fn::call_fn;
+
+/// vim.ui.select 参数
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SelectOpts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// 供 ui-select 插件区分调用场景
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+/// vim.ui.input 参数
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct InputOpts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// 补全类型，例如 `file`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion: Option<String>,
+}
+
+/// vim.ui.select
+/// 选中时回调 (下标(从 0 开始), 选项)，取消时回调 None
+pub fn select<F>(lua: &Lua, items: Vec<String>, opts: SelectOpts, on_choice: F) -> LuaResult<()>
+where
+    F: FnOnce(&Lua, Option<(usize, String)>) -> LuaResult<()> + 'static,
+{
+    let mut on_choice = Some(on_choice);
+    let callback =
+        lua.create_function_mut(move |lua, (item, index): (Option<String>, Option<usize>)| {
+            let choice = item.zip(index).map(|(item, index)| (index - 1, item));
+            match on_choice.take() {
+                Some(on_choice) => on_choice(lua, choice),
+                None => Ok(()),
+            }
+        })?;
+    call_select(lua, items, opts, callback)
+}
+
+/// vim.ui.input
+/// 确认时回调输入内容，取消时回调 None
+pub fn input<F>(lua: &Lua, opts: InputOpts, on_confirm: F) -> LuaResult<()>
+where
+    F: FnOnce(&Lua, Option<String>) -> LuaResult<()> + 'static,
+{
+    let mut on_confirm = Some(on_confirm);
+    let callback =
+        lua.create_function_mut(move |lua, input: Option<String>| match on_confirm.take() {
+            Some(on_confirm) => on_confirm(lua, input),
+            None => Ok(()),
+        })?;
+    let input: LuaFunction = lua.load("vim.ui.input").eval()?;
+    input.call((lua.to_value(&opts)?, callback))
+}
+
+/// vim.fn.confirm，阻塞等待用户选择
+/// choices 使用 `&` 标记快捷键，例如 `&Yes`，返回选中的下标(从 0 开始)，取消时返回 None
+pub fn confirm(
+    lua: &Lua,
+    message: &str,
+    choices: &[&str],
+    default: usize,
+) -> LuaResult<Option<usize>> {
+    let choice: usize = call_fn(lua, "confirm", (message, choices.join("\n"), default + 1))?;
+    Ok(choice.checked_sub(1))
+}
+
+/// select 的 future 版本，可以在 `register_async_function` 注册的函数中 await
+/// 不在协程中调用时返回错误，否则 future 永远不会完成
+pub fn select_async(
+    lua: &Lua,
+    items: Vec<String>,
+    opts: SelectOpts,
+) -> LuaResult<UiFuture<Option<(usize, String)>>> {
+    let (future, resolve) = UiFuture::new(lua)?;
+    select(lua, items, opts, move |lua, choice| resolve(lua, choice))?;
+    Ok(future)
+}
+
+/// input 的 future 版本，可以在 `register_async_function` 注册的函数中 await
+/// 不在协程中调用时返回错误，否则 future 永远不会完成
+pub fn input_async(lua: &Lua, opts: InputOpts) -> LuaResult<UiFuture<Option<String>>> {
+    let (future, resolve) = UiFuture::new(lua)?;
+    input(lua, opts, move |lua, input| resolve(lua, input))?;
+    Ok(future)
+}
+
+fn call_select(
+    lua: &Lua,
+    items: Vec<String>,
+    opts: SelectOpts,
+    callback: LuaFunction,
+) -> LuaResult<()> {
+    let select: LuaFunction = lua.load("vim.ui.select").eval()?;
+    select.call((items, lua.to_value(&opts)?, callback))
+}
+
+struct Slot<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// 等待 ui 回调的 future
+/// lua 侧调用的异步函数运行在协程中，回调完成后通过 vim.schedule 恢复协程
+pub struct UiFuture<T> {
+    slot: Rc<RefCell<Slot<T>>>,
+}
+
+/// 当前是否在协程中，LuaJIT 的主线程中 coroutine.running() 返回 nil 或 (主线程, true)
+const IN_COROUTINE: &str = r#"
+local co, main = coroutine.running()
+return co ~= nil and not main
+"#;
+
+type Resolve<T> = Box<dyn FnOnce(&Lua, T) -> LuaResult<()>>;
+
+impl<T: 'static> UiFuture<T> {
+    fn new(lua: &Lua) -> LuaResult<(UiFuture<T>, Resolve<T>)> {
+        let slot = Rc::new(RefCell::new(Slot {
+            value: None,
+            waker: None,
+        }));
+        let in_coroutine: bool = lua.load(IN_COROUTINE).eval()?;
+        if !in_coroutine {
+            return Err(LuaError::runtime(
+                "ui future must be awaited inside a coroutine!",
+            ));
+        }
+        let thread = lua.current_thread();
+        let resolve_slot = slot.clone();
+        let resolve = Box::new(move |lua: &Lua, value: T| {
+            let waker = {
+                let mut slot = resolve_slot.borrow_mut();
+                slot.value = Some(value);
+                slot.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+            resume_later(lua, thread)
+        });
+        Ok((UiFuture { slot }, resolve))
+    }
+}
+
+impl<T> Future for UiFuture<T> {
+    type Output = LuaResult<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.borrow_mut();
+        match slot.value.take() {
+            Some(value) => Poll::Ready(Ok(value)),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// 下一轮事件循环中恢复挂起的协程，协程恢复后重新 poll future
+fn resume_later(lua: &Lua, thread: LuaThread) -> LuaResult<()> {
+    if thread.status() != LuaThreadStatus::Resumable {
+        return Ok(());
+    }
+    let resume = lua.create_function(move |_, ()| {
+        if thread.status() == LuaThreadStatus::Resumable {
+            thread.resume::<()>(())?;
+        }
+        Ok(())
+    })?;
+    let schedule: LuaFunction = lua.load("vim.schedule").eval()?;
+    schedule.call(resume)
+}
@@ -72,6 +72,7 @@ impl<'lua> RootPlugin<'lua> {
 
     /// 注册插件为全局插件
     pub fn register_to_global(&self) -> LuaResult<()> {
+        api::timer::clear_group(self.runtime(), self.name())?;
         self.init()?;
         let globals = self.runtime().globals();
         globals.set(self.name(), self.plugin())?;
@@ -83,6 +84,8 @@ impl<'lua> RootPlugin<'lua> {
     where
         P: Plugin<'lua>,
     {
+        // 重新初始化时取消插件遗留的定时器
+        api::timer::clear_group(self.runtime(), child_plugin.name())?;
         child_plugin.init()?;
         self.plugin()
             .set(child_plugin.name(), child_plugin.plugin())?;
@@ -0,0 +1,103 @@
+use api::range::{Point, Range};
+use api::text_edit::TextEdit;
+
+/// 文本是否被块注释包裹，忽略首尾空白
+pub fn is_block_commented(text: &str, open: &str, close: &str) -> bool {
+    let inner = text.trim();
+    inner.len() >= open.len() + close.len() && inner.starts_with(open) && inner.ends_with(close)
+}
+
+/// 切换 range 内文本的块注释，text 为 range 内的原文本
+/// 已被块注释包裹时删除注释符号及其内侧的一个空格，否则在首尾空白之内添加
+pub fn block_toggle(text: &str, range: Range, open: &str, close: &str) -> Vec<TextEdit> {
+    let inner = text.trim();
+    if inner.is_empty() {
+        return Vec::new();
+    }
+    let inner_start = text.len() - text.trim_start().len();
+    let inner_end = inner_start + inner.len();
+    let at = |offset: usize| range.start.advance(&text[..offset]);
+    if is_block_commented(text, open, close) {
+        let body = &inner[open.len()..inner.len() - close.len()];
+        let open_space = usize::from(body.starts_with(' '));
+        let close_space = usize::from(body.len() > open_space && body.ends_with(' '));
+        let open_end = inner_start + open.len() + open_space;
+        let close_start = inner_end - close.len() - close_space;
+        vec![
+            TextEdit::delete(Range::new(at(inner_start), at(open_end))),
+            TextEdit::delete(Range::new(at(close_start), at(inner_end))),
+        ]
+    } else {
+        vec![
+            TextEdit::insert(at(inner_start), format!("{open} ").as_str()),
+            TextEdit::insert(at(inner_end), format!(" {close}").as_str()),
+        ]
+    }
+}
+
+/// 逐行切换块注释，用于没有行注释的文件类型
+/// 存在未注释的非空行时注释这些行，否则取消全部注释
+pub fn block_lines_toggle(
+    lines: &[String],
+    start_row: usize,
+    open: &str,
+    close: &str,
+) -> Vec<TextEdit> {
+    let commented = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| is_block_commented(line, open, close));
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            !line.trim().is_empty() && is_block_commented(line, open, close) == commented
+        })
+        .flat_map(|(offset, line)| {
+            let row = start_row + offset;
+            let range = Range::new(Point::new(row, 0), Point::new(row, line.len()));
+            block_toggle(line, range, open, close)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_toggle_works() {
+        let range = Range::new(Point::new(2, 4), Point::new(3, 5));
+        let edits = block_toggle("a = 1;\nb = 2", range, "/*", "*/");
+        assert_eq!(
+            edits,
+            vec![
+                TextEdit::insert(Point::new(2, 4), "/* "),
+                TextEdit::insert(Point::new(3, 5), " */"),
+            ]
+        );
+        let range = Range::new(Point::new(0, 0), Point::new(0, 15));
+        let edits = block_toggle("<!-- <br> -->  ", range, "<!--", "-->");
+        assert_eq!(
+            edits,
+            vec![
+                TextEdit::delete(Range::new(Point::new(0, 0), Point::new(0, 5))),
+                TextEdit::delete(Range::new(Point::new(0, 9), Point::new(0, 13))),
+            ]
+        );
+    }
+
+    #[test]
+    fn block_lines_toggle_works() {
+        let lines = vec!["  a {}".to_string(), "".to_string(), "/* b */".to_string()];
+        let edits = block_lines_toggle(&lines, 5, "/*", "*/");
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range.start, Point::new(5, 2));
+        let lines = vec!["/* a */".to_string()];
+        let edits = block_lines_toggle(&lines, 0, "/*", "*/");
+        assert_eq!(
+            edits[1].range,
+            Range::new(Point::new(0, 4), Point::new(0, 7))
+        );
+    }
+}
@@ -1,19 +1,249 @@
 use std::collections::HashMap;
 
+use mlua::Lua;
 use once_cell::sync::Lazy;
+use serde::Deserialize;
 
-static GLOBAL_CONFIG: Lazy<HashMap<String, String>> = Lazy::new(|| {
-    let mut map = HashMap::new();
-    map.insert("c".to_string(), "//".to_string());
-    map.insert("sh".to_string(), "#".to_string());
-    map.insert("cpp".to_string(), "//".to_string());
-    map.insert("lua".to_string(), "--".to_string());
-    map.insert("sql".to_string(), "--".to_string());
-    map.insert("rust".to_string(), "//".to_string());
-    map
+use crate::doc::DocStyle;
+
+/// 注释配置
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct CommentConfig {
+    /// 行注释前缀，例如 `//`
+    pub line: Option<String>,
+    /// 块注释，例如 (`/*`, `*/`)
+    pub block: Option<(String, String)>,
+}
+
+impl CommentConfig {
+    fn new(line: Option<&str>, block: Option<(&str, &str)>) -> CommentConfig {
+        CommentConfig {
+            line: line.map(|x| x.to_string()),
+            block: block.map(|(open, close)| (open.to_string(), close.to_string())),
+        }
+    }
+
+    /// 解析 'commentstring'，例如 `// %s` / `/* %s */`
+    /// 没有后缀时为行注释，否则为块注释
+    pub fn parse(commentstring: &str) -> Option<CommentConfig> {
+        let (prefix, suffix) = commentstring.split_once("%s")?;
+        let (prefix, suffix) = (prefix.trim_end(), suffix.trim_start());
+        if prefix.is_empty() {
+            return None;
+        }
+        if suffix.is_empty() {
+            Some(CommentConfig::new(Some(prefix), None))
+        } else {
+            Some(CommentConfig::new(None, Some((prefix, suffix))))
+        }
+    }
+
+    /// 逐项合并，self 优先
+    pub fn or(self, other: CommentConfig) -> CommentConfig {
+        CommentConfig {
+            line: self.line.or(other.line),
+            block: self.block.or(other.block),
+        }
+    }
+
+    /// 行中第一个注释前缀之后的字节位置，块注释使用开始符号
+    pub fn leader_end(&self, line: &str) -> Option<usize> {
+        self.line
+            .iter()
+            .chain(self.block.iter().map(|(open, _)| open))
+            .filter_map(|leader| line.find(leader.as_str()).map(|index| index + leader.len()))
+            .min()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.line.is_none() && self.block.is_none()
+    }
+}
+
+/// setup 参数
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SetupOpts {
+    /// 按文件类型覆盖注释配置
+    pub filetypes: HashMap<String, CommentConfig>,
+    /// 按文件类型覆盖文档注释风格，例如 `{ c = "javadoc", lua = "none" }`
+    pub docs: HashMap<String, DocStyle>,
+    /// 注释中标记的高亮组，覆盖内置配置，高亮组为空时不高亮该标记
+    pub highlights: HashMap<String, String>,
+}
+
+/// 用户配置，保存在 lua app data 中
+struct UserConfig(SetupOpts);
+
+const C_BLOCK: (&str, &str) = ("/*", "*/");
+const HTML_BLOCK: (&str, &str) = ("<!--", "-->");
+const LUA_BLOCK: (&str, &str) = ("--[[", "]]");
+
+static GLOBAL_CONFIG: Lazy<HashMap<String, CommentConfig>> = Lazy::new(|| {
+    [
+        ("c", Some("//"), Some(C_BLOCK)),
+        ("sh", Some("#"), None),
+        ("cpp", Some("//"), Some(C_BLOCK)),
+        ("lua", Some("--"), Some(LUA_BLOCK)),
+        ("sql", Some("--"), Some(C_BLOCK)),
+        ("rust", Some("//"), Some(C_BLOCK)),
+        ("css", None, Some(C_BLOCK)),
+        ("html", None, Some(HTML_BLOCK)),
+        ("xml", None, Some(HTML_BLOCK)),
+    ]
+    .into_iter()
+    .map(|(filetype, line, block)| (filetype.to_string(), CommentConfig::new(line, block)))
+    .collect()
 });
 
+/// 注释中标记的内置高亮组
+const HIGHLIGHTS: &[(&str, &str)] = &[
+    ("TODO", "Todo"),
+    ("FIXME", "DiagnosticError"),
+    ("BUG", "DiagnosticError"),
+    ("HACK", "DiagnosticWarn"),
+    ("WARN", "DiagnosticWarn"),
+    ("SAFETY", "DiagnosticWarn"),
+    ("PERF", "DiagnosticHint"),
+    ("NOTE", "DiagnosticInfo"),
+];
+
+pub fn setup(lua: &Lua, opts: SetupOpts) {
+    lua.set_app_data(UserConfig(opts));
+}
+
+/// 文档注释风格，优先级：用户配置 > 内置配置，None 表示不生成
+pub fn doc_style(lua: &Lua, filetype: &str) -> Option<DocStyle> {
+    let user = lua
+        .app_data_ref::<UserConfig>()
+        .and_then(|config| config.0.docs.get(filetype).copied());
+    user.or_else(|| DocStyle::builtin(filetype))
+        .filter(|&style| style != DocStyle::None)
+}
+
+/// 注释配置，用户配置替换内置配置，缺少的部分使用 'commentstring'
+pub fn resolve(lua: &Lua, filetype: &str, commentstring: &str) -> Option<CommentConfig> {
+    let user = lua
+        .app_data_ref::<UserConfig>()
+        .and_then(|config| config.0.filetypes.get(filetype).cloned());
+    merge(user, filetype, commentstring)
+}
+
+/// 标记及其高亮组，用户配置优先
+pub fn highlights(lua: &Lua) -> Vec<(String, String)> {
+    let user = lua
+        .app_data_ref::<UserConfig>()
+        .map(|config| config.0.highlights.clone())
+        .unwrap_or_default();
+    merge_highlights(user)
+}
+
+fn merge_highlights(user: HashMap<String, String>) -> Vec<(String, String)> {
+    let mut highlights = HIGHLIGHTS
+        .iter()
+        .filter(|(tag, _)| !user.contains_key(*tag))
+        .map(|&(tag, group)| (tag.to_string(), group.to_string()))
+        .collect::<Vec<_>>();
+    highlights.extend(user);
+    highlights.retain(|(_, group)| !group.is_empty());
+    highlights.sort();
+    highlights
+}
+
+/// 依次尝试 (文件类型, 'commentstring')，返回第一个有效的注释配置
+pub fn resolve_first(lua: &Lua, candidates: &[(String, String)]) -> Option<CommentConfig> {
+    let user = lua.app_data_ref::<UserConfig>();
+    first_config(candidates, |filetype| {
+        user.as_ref()
+            .and_then(|config| config.0.filetypes.get(filetype).cloned())
+    })
+}
+
+fn first_config<F>(candidates: &[(String, String)], user: F) -> Option<CommentConfig>
+where
+    F: Fn(&str) -> Option<CommentConfig>,
+{
+    candidates
+        .iter()
+        .find_map(|(filetype, commentstring)| merge(user(filetype), filetype, commentstring))
+}
+
+fn merge(
+    user: Option<CommentConfig>,
+    filetype: &str,
+    commentstring: &str,
+) -> Option<CommentConfig> {
+    // 用户配置整体替换内置配置，缺少的部分由 'commentstring' 补充
+    let config = user
+        .or_else(|| GLOBAL_CONFIG.get(filetype).cloned())
+        .unwrap_or_default();
+    let fallback = CommentConfig::parse(commentstring).unwrap_or_default();
+    let config = config.or(fallback);
+    (!config.is_empty()).then_some(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        assert_eq!(
+            CommentConfig::parse("# %s"),
+            Some(CommentConfig::new(Some("#"), None))
+        );
+        assert_eq!(
+            CommentConfig::parse("<!--%s-->"),
+            Some(CommentConfig::new(None, Some(HTML_BLOCK)))
+        );
+        assert_eq!(CommentConfig::parse(""), None);
+        assert_eq!(CommentConfig::parse("%s"), None);
+    }
+
+    #[test]
+    fn merge_works() {
+        let config = merge(None, "html", "<!-- %s -->").unwrap();
+        assert_eq!(config, CommentConfig::new(None, Some(HTML_BLOCK)));
+        let config = merge(None, "python", "# %s").unwrap();
+        assert_eq!(config.line.as_deref(), Some("#"));
+        assert_eq!(merge(None, "text", ""), None);
+        let user = CommentConfig::new(Some("///"), None);
+        let config = merge(Some(user), "rust", "/* %s */").unwrap();
+        assert_eq!(config, CommentConfig::new(Some("///"), Some(C_BLOCK)));
+        // 只配置块注释时不继承内置的行注释
+        let user = CommentConfig::new(None, Some(C_BLOCK));
+        let config = merge(Some(user), "c", "/* %s */").unwrap();
+        assert_eq!(config, CommentConfig::new(None, Some(C_BLOCK)));
+    }
+
+    #[test]
+    fn first_config_works() {
+        // markdown 段落注入的 markdown_inline 没有注释配置，使用外层语言
+        let candidates = [("markdown_inline", ""), ("markdown", "<!-- %s -->")]
+            .map(|(filetype, commentstring)| (filetype.to_string(), commentstring.to_string()));
+        let config = first_config(&candidates, |_| None).unwrap();
+        assert_eq!(config, CommentConfig::new(None, Some(HTML_BLOCK)));
+        let config = first_config(&candidates[..1], |_| None);
+        assert_eq!(config, None);
+    }
 
-pub fn comment_string(filetype: String) -> Option<String> {
-    GLOBAL_CONFIG.get(filetype.as_str()).map(|x| x.clone())
+    #[test]
+    fn merge_highlights_works() {
+        let user = [("TODO", "Error"), ("NOTE", ""), ("XXX", "Todo")]
+            .into_iter()
+            .map(|(tag, group)| (tag.to_string(), group.to_string()))
+            .collect();
+        let highlights = merge_highlights(user);
+        let group = |tag: &str| {
+            highlights
+                .iter()
+                .find(|(x, _)| x == tag)
+                .map(|(_, group)| group.as_str())
+        };
+        assert_eq!(group("TODO"), Some("Error"));
+        assert_eq!(group("NOTE"), None);
+        assert_eq!(group("XXX"), Some("Todo"));
+        assert_eq!(group("FIXME"), Some("DiagnosticError"));
+    }
 }
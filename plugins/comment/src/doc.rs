@@ -0,0 +1,292 @@
+use api::range::Range;
+use api::treesitter::{Capture, Node};
+use mlua::Lua;
+use mlua::prelude::LuaResult;
+use serde::Deserialize;
+
+/// 文档注释风格
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DocStyle {
+    /// `///`，带 `# Errors` / `# Panics` 小节
+    Rust,
+    /// `---@param` / `---@return`
+    EmmyLua,
+    /// `/** ... */`，带 `@param` / `@return` / `@throws`
+    Javadoc,
+    /// 不生成
+    None,
+}
+
+impl DocStyle {
+    /// 文件类型的默认风格
+    pub fn builtin(filetype: &str) -> Option<DocStyle> {
+        match filetype {
+            "rust" => Some(DocStyle::Rust),
+            "lua" => Some(DocStyle::EmmyLua),
+            "java" => Some(DocStyle::Javadoc),
+            _ => None,
+        }
+    }
+}
+
+/// 语言的语法节点信息
+pub struct DocLanguage {
+    /// 可添加文档注释的节点类型
+    pub items: &'static [&'static str],
+    /// 其中的函数节点类型
+    pub functions: &'static [&'static str],
+    /// 捕获 `param` / `return` / `throws` / `body` / `body_return`
+    pub query: &'static str,
+    /// 位于节点之前、文档注释需要放在其上方的属性行前缀
+    pub attribute: Option<&'static str>,
+}
+
+const RUST_QUERY: &str = r#"
+(function_item parameters: (parameters (parameter pattern: (_) @param)))
+(function_signature_item parameters: (parameters (parameter pattern: (_) @param)))
+(function_item return_type: (_) @return)
+(function_signature_item return_type: (_) @return)
+(function_item body: (block) @body)
+"#;
+
+const LUA_QUERY: &str = r#"
+(function_declaration parameters: (parameters (identifier) @param))
+(function_definition parameters: (parameters (identifier) @param))
+(function_declaration body: (block) @body)
+(function_definition body: (block) @body)
+(return_statement) @body_return
+"#;
+
+const JAVA_QUERY: &str = r#"
+(formal_parameters (formal_parameter name: (identifier) @param))
+(method_declaration type: (_) @return)
+(throws (_) @throws)
+(method_declaration body: (block) @body)
+(constructor_declaration body: (constructor_body) @body)
+"#;
+
+/// treesitter 语言对应的节点信息
+pub fn language(lang: &str) -> Option<DocLanguage> {
+    match lang {
+        "rust" => Some(DocLanguage {
+            items: &[
+                "function_item",
+                "function_signature_item",
+                "struct_item",
+                "enum_item",
+                "union_item",
+                "trait_item",
+                "type_item",
+                "const_item",
+                "static_item",
+                "mod_item",
+                "macro_definition",
+            ],
+            functions: &["function_item", "function_signature_item"],
+            query: RUST_QUERY,
+            attribute: Some("#["),
+        }),
+        "lua" => Some(DocLanguage {
+            items: &["function_declaration", "function_definition"],
+            functions: &["function_declaration", "function_definition"],
+            query: LUA_QUERY,
+            attribute: None,
+        }),
+        "java" => Some(DocLanguage {
+            items: &[
+                "method_declaration",
+                "constructor_declaration",
+                "class_declaration",
+                "interface_declaration",
+                "enum_declaration",
+                "record_declaration",
+                "field_declaration",
+            ],
+            functions: &["method_declaration", "constructor_declaration"],
+            query: JAVA_QUERY,
+            attribute: None,
+        }),
+        _ => None,
+    }
+}
+
+/// 生成文档注释所需的签名信息
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocInfo {
+    pub function: bool,
+    pub params: Vec<String>,
+    /// 返回类型，类型未知时为 `any`
+    pub returns: Option<String>,
+    pub throws: Vec<String>,
+    /// 函数体中是否可能 panic
+    pub panics: bool,
+}
+
+/// 可能 panic 的调用
+const PANIC_PATTERNS: &[&str] = &[
+    "panic!",
+    "unreachable!",
+    "todo!",
+    "unimplemented!",
+    "assert",
+    ".unwrap()",
+    ".expect(",
+];
+
+/// 收集节点的签名信息
+/// 参数等只取函数体之前的捕获，排除函数体内嵌套的函数
+pub fn collect(lua: &Lua, item: &Node, language: &DocLanguage) -> LuaResult<DocInfo> {
+    let captures = api::treesitter::query(
+        lua,
+        0,
+        Some(item.lang.as_str()),
+        language.query,
+        item.range.start.row,
+        item.range.end.row + 1,
+    )?
+    .into_iter()
+    .filter(|x| item.range.start <= x.node.range.start && x.node.range.end <= item.range.end)
+    .collect::<Vec<_>>();
+    let body_start = captures
+        .iter()
+        .filter(|x| x.name == "body")
+        .map(|x| x.node.range.start)
+        .min()
+        .unwrap_or(item.range.end);
+    let text = |range: Range| -> LuaResult<String> {
+        Ok(api::buffer::get_text(lua, 0, range)?.join("\n"))
+    };
+    let signature = |name: &str| -> LuaResult<Vec<String>> {
+        captures
+            .iter()
+            .filter(|x| x.name == name && x.node.range.start < body_start)
+            .map(|x: &Capture| text(x.node.range))
+            .collect()
+    };
+    let body_returns = captures
+        .iter()
+        .any(|x| x.name == "body_return" && x.node.range.start >= body_start);
+    let returns = signature("return")?
+        .into_iter()
+        .find(|x| x != "void")
+        .or_else(|| body_returns.then(|| "any".to_string()));
+    let body = text(Range::new(body_start, item.range.end))?;
+    Ok(DocInfo {
+        function: language.functions.contains(&item.kind.as_str()),
+        params: signature("param")?,
+        returns,
+        throws: signature("throws")?,
+        panics: PANIC_PATTERNS.iter().any(|x| body.contains(x)),
+    })
+}
+
+/// 文档注释的插入行，跳过节点上方紧邻的属性行
+pub fn insert_row(lines: &[String], row: usize, attribute: Option<&str>) -> usize {
+    let Some(attribute) = attribute else {
+        return row;
+    };
+    (0..row)
+        .rev()
+        .take_while(|&row| lines[row].trim_start().starts_with(attribute))
+        .last()
+        .unwrap_or(row)
+}
+
+/// 文档注释模板，不含缩进
+pub struct DocTemplate {
+    pub lines: Vec<String>,
+    /// 光标所在行，光标位于行尾
+    pub cursor_row: usize,
+}
+
+pub fn render(style: DocStyle, info: &DocInfo) -> DocTemplate {
+    let mut lines = Vec::new();
+    let cursor_row = match style {
+        DocStyle::Rust => {
+            lines.push("/// ".to_string());
+            let errors = info.returns.as_ref().is_some_and(|x| x.contains("Result"));
+            for (section, enabled) in [("Errors", errors), ("Panics", info.panics)] {
+                if info.function && enabled {
+                    lines.push("///".to_string());
+                    lines.push(format!("/// # {section}"));
+                }
+            }
+            0
+        }
+        DocStyle::EmmyLua => {
+            lines.push("--- ".to_string());
+            lines.extend(info.params.iter().map(|x| format!("---@param {x} any")));
+            if let Some(returns) = &info.returns {
+                lines.push(format!("---@return {returns}"));
+            }
+            0
+        }
+        DocStyle::Javadoc => {
+            lines.push("/**".to_string());
+            lines.push(" * ".to_string());
+            let mut tags = info
+                .params
+                .iter()
+                .map(|x| format!(" * @param {x}"))
+                .collect::<Vec<_>>();
+            if info.returns.is_some() {
+                tags.push(" * @return".to_string());
+            }
+            tags.extend(info.throws.iter().map(|x| format!(" * @throws {x}")));
+            if !tags.is_empty() {
+                lines.push(" *".to_string());
+                lines.extend(tags);
+            }
+            lines.push(" */".to_string());
+            1
+        }
+        DocStyle::None => 0,
+    };
+    DocTemplate { lines, cursor_row }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_works() {
+        let info = DocInfo {
+            function: true,
+            params: vec!["a".to_string(), "b".to_string()],
+            returns: Some("Result<u8>".to_string()),
+            throws: vec!["IOException".to_string()],
+            panics: true,
+        };
+        let template = render(DocStyle::Rust, &info);
+        assert_eq!(
+            template.lines,
+            vec!["/// ", "///", "/// # Errors", "///", "/// # Panics"]
+        );
+        let template = render(DocStyle::EmmyLua, &info);
+        assert_eq!(
+            template.lines,
+            vec![
+                "--- ",
+                "---@param a any",
+                "---@param b any",
+                "---@return Result<u8>"
+            ]
+        );
+        let template = render(DocStyle::Javadoc, &DocInfo::default());
+        assert_eq!(template.lines, vec!["/**", " * ", " */"]);
+        assert_eq!(template.cursor_row, 1);
+    }
+
+    #[test]
+    fn insert_row_works() {
+        let lines = ["", "#[derive(Debug)]", "  #[serde(default)]", "struct A;"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(insert_row(&lines, 3, Some("#[")), 1);
+        assert_eq!(insert_row(&lines, 3, None), 3);
+        assert_eq!(insert_row(&lines, 0, Some("#[")), 0);
+    }
+}
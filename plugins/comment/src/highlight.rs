@@ -0,0 +1,238 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use api::builtin_fn::call_lua;
+use api::extmark;
+use api::mirror::{BufferMirror, LineChange};
+use api::option::{self, Scope};
+use api::range::{Point, Range};
+use api::timer::Debounce;
+use mlua::prelude::*;
+
+use crate::config::{self, CommentConfig};
+use crate::todo;
+
+const NAMESPACE: &str = "comment_highlight";
+
+/// 变更后延迟刷新的毫秒数
+const DELAY: u64 = 100;
+
+/// vim.log.levels.ERROR
+const LOG_LEVEL_ERROR: u8 = 4;
+
+const AUGROUP: &str = "comment_highlight";
+
+/// 定时器分组，插件重新初始化时取消
+const TIMER_GROUP: &str = "comment";
+
+/// 已挂载的 buffer
+struct Attached {
+    mirror: BufferMirror,
+    debounce: Rc<Debounce<()>>,
+}
+
+/// 已挂载的 buffer，保存在 lua app data 中
+#[derive(Default)]
+struct Mirrors(HashMap<usize, Attached>);
+
+type BufferCallback = fn(&Lua, usize) -> LuaResult<()>;
+
+/// 文件类型确定后挂载 buffer 并高亮，buffer 卸载时取消挂载
+/// autocmd 位于同一个 augroup 中，重复调用时替换之前的 autocmd
+/// 插件重新初始化时分组内的定时器已被关闭，已挂载的 buffer 需要重新挂载
+pub fn enable(lua: &Lua) -> LuaResult<()> {
+    let buffers = lua
+        .app_data_ref::<Mirrors>()
+        .map(|mirrors| mirrors.0.keys().copied().collect::<Vec<_>>())
+        .unwrap_or_default();
+    for &buffer in &buffers {
+        detach(lua, buffer)?;
+    }
+    let create_augroup: LuaFunction = lua.load("vim.api.nvim_create_augroup").eval()?;
+    let augroup_opts = lua.create_table()?;
+    augroup_opts.set("clear", true)?;
+    let group: u32 = create_augroup.call((AUGROUP, augroup_opts))?;
+    let create: LuaFunction = lua.load("vim.api.nvim_create_autocmd").eval()?;
+    let autocmds: [(&str, BufferCallback); 2] = [("FileType", attach), ("BufUnload", detach)];
+    for (event, callback) in autocmds {
+        let opts = lua.create_table()?;
+        opts.set("group", group)?;
+        opts.set(
+            "callback",
+            lua.create_function(move |lua, args: LuaTable| {
+                callback(lua, args.get("buf")?)?;
+                Ok(false)
+            })?,
+        )?;
+        create.call::<u32>((event, opts))?;
+    }
+    for buffer in buffers {
+        attach(lua, buffer)?;
+    }
+    Ok(())
+}
+
+/// 挂载 buffer，全量高亮后只刷新变更的行
+pub fn attach(lua: &Lua, buffer: usize) -> LuaResult<()> {
+    let namespace = extmark::namespace(lua, NAMESPACE)?;
+    let attached = lua
+        .app_data_ref::<Mirrors>()
+        .and_then(|mirrors| mirrors.0.get(&buffer).map(|x| x.mirror.clone()))
+        .filter(BufferMirror::is_attached);
+    if let Some(mirror) = attached {
+        return refresh(lua, &mirror, namespace, 0, usize::MAX);
+    }
+    detach(lua, buffer)?;
+    let mirror = BufferMirror::attach(lua, buffer)?;
+    let dirty = Rc::new(RefCell::new(None));
+    let pending = dirty.clone();
+    let target = mirror.clone();
+    let debounce = Rc::new(Debounce::with_group(
+        lua,
+        TIMER_GROUP,
+        DELAY,
+        move |lua, ()| {
+            let Some((start_row, end_row)) = pending.take() else {
+                return Ok(());
+            };
+            refresh(lua, &target, namespace, start_row, end_row)
+        },
+    )?);
+    let trigger = debounce.clone();
+    let weak = lua.weak();
+    mirror.subscribe(move |_, change| {
+        let range = merge_dirty(*dirty.borrow(), change);
+        dirty.replace(Some(range));
+        if let Err(err) = trigger.call(())
+            && let Some(lua) = weak.try_upgrade()
+        {
+            let message = format!("comment highlight: {err}");
+            let _ = call_lua::<_, ()>(&lua, "vim.notify", (message, LOG_LEVEL_ERROR));
+        }
+    });
+    refresh(lua, &mirror, namespace, 0, usize::MAX)?;
+    if lua.app_data_ref::<Mirrors>().is_none() {
+        lua.set_app_data(Mirrors::default());
+    }
+    if let Some(mut mirrors) = lua.app_data_mut::<Mirrors>() {
+        mirrors.0.insert(buffer, Attached { mirror, debounce });
+    }
+    Ok(())
+}
+
+/// 取消挂载并关闭定时器
+pub fn detach(lua: &Lua, buffer: usize) -> LuaResult<()> {
+    let attached = lua
+        .app_data_mut::<Mirrors>()
+        .and_then(|mut mirrors| mirrors.0.remove(&buffer));
+    if let Some(attached) = attached {
+        attached.mirror.detach();
+        attached.debounce.close(lua)?;
+    }
+    Ok(())
+}
+
+/// 合并待刷新的行范围 [start_row, end_row)，已有范围按本次变更平移
+fn merge_dirty(dirty: Option<(usize, usize)>, change: &LineChange) -> (usize, usize) {
+    let (first, last, new_last) = (change.first_line, change.last_line, change.new_last_line);
+    match dirty {
+        Some((start_row, end_row)) => {
+            let end_row = if end_row >= last {
+                end_row + new_last - last
+            } else {
+                end_row.max(new_last)
+            };
+            (start_row.min(first), end_row.max(new_last))
+        }
+        None => (first, new_last),
+    }
+}
+
+/// 重新高亮行范围 [start_row, end_row)
+fn refresh(
+    lua: &Lua,
+    mirror: &BufferMirror,
+    namespace: u32,
+    start_row: usize,
+    end_row: usize,
+) -> LuaResult<()> {
+    if !mirror.is_attached() {
+        return Ok(());
+    }
+    let buffer = mirror.buffer();
+    let end_row = end_row.min(mirror.line_count());
+    extmark::clear(lua, namespace, buffer, start_row, Some(end_row))?;
+    let highlights = config::highlights(lua);
+    let tags = highlights
+        .iter()
+        .map(|(tag, _)| tag.as_str())
+        .collect::<Vec<_>>();
+    let fallback = match api::treesitter::has_parser(lua, buffer)? {
+        true => None,
+        false => comment_config(lua, buffer)?,
+    };
+    for row in start_row..end_row {
+        let line = mirror.line(row).unwrap_or_default();
+        for tag in todo::find_tags(&line, &tags) {
+            let in_comment = match &fallback {
+                Some(config) => config
+                    .leader_end(&line)
+                    .is_some_and(|leader_end| tag.start >= leader_end),
+                None => in_comment(lua, buffer, Point::new(row, tag.start))?,
+            };
+            if in_comment {
+                let range = Range::new(Point::new(row, tag.start), Point::new(row, tag.end));
+                extmark::highlight(
+                    lua,
+                    namespace,
+                    buffer,
+                    range,
+                    &highlights[tag.index].1,
+                    None,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 位置是否在注释节点中
+/// 注释可能被注入的 `comment` parser 解析为 `tag` / `text` 等节点，需要同时检查外层语言树
+fn in_comment(lua: &Lua, buffer: usize, point: Point) -> LuaResult<bool> {
+    for ignore_injections in [false, true] {
+        let nodes = api::treesitter::ancestors(lua, buffer, point, ignore_injections)?;
+        if nodes.iter().any(|node| node.kind.contains("comment")) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// 没有 treesitter parser 时按 buffer 的注释配置判断
+fn comment_config(lua: &Lua, buffer: usize) -> LuaResult<Option<CommentConfig>> {
+    let filetype: String = option::get(lua, "filetype", Scope::Buffer(buffer))?;
+    let commentstring: String = option::get(lua, "commentstring", Scope::Buffer(buffer))?;
+    Ok(config::resolve(
+        lua,
+        filetype.as_str(),
+        commentstring.as_str(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_dirty_works() {
+        let change = LineChange {
+            first_line: 2,
+            last_line: 3,
+            new_last_line: 5,
+            changedtick: 0,
+        };
+        assert_eq!(merge_dirty(None, &change), (2, 5));
+        assert_eq!(merge_dirty(Some((0, 4)), &change), (0, 6));
+    }
+}
@@ -0,0 +1,111 @@
+use api::option::{self, Scope};
+use mlua::Lua;
+use mlua::prelude::LuaResult;
+
+/// 缩进相关的 buffer 选项
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndentOpts {
+    pub tabstop: usize,
+    pub expandtab: bool,
+}
+
+impl Default for IndentOpts {
+    fn default() -> Self {
+        IndentOpts {
+            tabstop: 8,
+            expandtab: false,
+        }
+    }
+}
+
+impl IndentOpts {
+    /// 当前 buffer 的 'tabstop' / 'expandtab'
+    pub fn current(lua: &Lua) -> LuaResult<IndentOpts> {
+        Ok(IndentOpts {
+            tabstop: option::get(lua, "tabstop", Scope::Buffer(0))?,
+            expandtab: option::get(lua, "expandtab", Scope::Buffer(0))?,
+        })
+    }
+
+    /// 位于显示列 col 的字符 c 的宽度
+    fn width(&self, c: char, col: usize) -> usize {
+        match c {
+            '\t' => self.tabstop.max(1) - col % self.tabstop.max(1),
+            _ => 1,
+        }
+    }
+}
+
+/// 是否为缩进空白
+pub fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+/// 只包含空白的行
+pub fn is_blank_line(line: &str) -> bool {
+    line.chars().all(is_blank)
+}
+
+/// 行首空白的字节长度
+pub fn indent_len(line: &str) -> usize {
+    line.find(|c: char| !is_blank(c)).unwrap_or(line.len())
+}
+
+/// 行首空白的显示宽度
+pub fn indent_width(line: &str, opts: IndentOpts) -> usize {
+    display_width(&line[..indent_len(line)], opts)
+}
+
+/// 从行首开始的文本的显示宽度，tab 按 'tabstop' 展开，其他字符宽度为 1
+pub fn display_width(text: &str, opts: IndentOpts) -> usize {
+    text.chars().fold(0, |col, c| col + opts.width(c, col))
+}
+
+/// 在行首空白的显示列 col 处插入 text
+/// col 落在 tab 中间时不拆开 tab：expandtab 时将该 tab 展开为空格，否则插入到该 tab 之前
+pub fn insert_at_column(line: &str, col: usize, text: &str, opts: IndentOpts) -> String {
+    let mut width = 0;
+    for (index, c) in line.char_indices() {
+        if width >= col || !is_blank(c) {
+            return format!("{}{}{}", &line[..index], text, &line[index..]);
+        }
+        let next = width + opts.width(c, width);
+        if next > col {
+            if !opts.expandtab {
+                return format!("{}{}{}", &line[..index], text, &line[index..]);
+            }
+            return format!(
+                "{}{}{}{}{}",
+                &line[..index],
+                " ".repeat(col - width),
+                text,
+                " ".repeat(next - col),
+                &line[index + c.len_utf8()..]
+            );
+        }
+        width = next;
+    }
+    format!("{line}{text}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_at_column_works() {
+        let opts = IndentOpts {
+            tabstop: 4,
+            expandtab: false,
+        };
+        assert_eq!(indent_width("\t  a", opts), 6);
+        assert_eq!(insert_at_column("\t\ta", 4, "// ", opts), "\t// \ta");
+        assert_eq!(insert_at_column("    a", 4, "// ", opts), "    // a");
+        assert_eq!(insert_at_column("\ta", 2, "// ", opts), "// \ta");
+        let opts = IndentOpts {
+            expandtab: true,
+            ..opts
+        };
+        assert_eq!(insert_at_column("\ta", 2, "// ", opts), "  //   a");
+    }
+}
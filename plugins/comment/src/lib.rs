@@ -1,9 +1,29 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use api::command::Command;
+use api::option::Scope;
+use api::quickfix::{ListKind, QuickfixItem};
+use api::range::{Point, Range};
+use api::text_edit::TextEdit;
+use api::ui::SelectOpts;
 use mlua::prelude::*;
 use nvim_oxi::api::opts::SetKeymapOpts;
 use nvim_oxi::api::types::Mode;
 use plugin::{Plugin, ROOT_PLUGINS_NAME};
 
+use crate::config::{CommentConfig, SetupOpts};
+use crate::indent::IndentOpts;
+use crate::todo::{TodoItem, TodoOpts};
+
+mod block;
 mod config;
+mod doc;
+mod highlight;
+mod indent;
+mod textobject;
+mod todo;
+mod trailing;
 
 /// 代码注释插件
 pub struct Comment<'lua> {
@@ -26,33 +46,120 @@ impl<'lua> Plugin<'lua> for Comment<'lua> {
     fn init(&self) -> LuaResult<()> {
         let comment_line_func_name = "comment_line_toggle";
         let comment_multiline_func_name = "comment_multiline_toggle";
+        let comment_block_func_name = "comment_block_toggle";
+        let comment_operator_func_name = "comment_operator";
+        let comment_operator_expr_func_name = "comment_operator_expr";
+        let comment_textobject_func_name = "comment_textobject";
+        let comment_append_func_name = "comment_append";
+        let comment_align_func_name = "comment_align";
+        let comment_doc_func_name = "comment_doc";
+        self.register_function("setup", setup)?;
         self.register_function(comment_line_func_name, comment_line_toggle_export)?;
         self.register_function(comment_multiline_func_name, comment_multiline_toggle_export)?;
+        self.register_function(comment_block_func_name, comment_block_toggle_export)?;
+        self.register_function(comment_operator_func_name, comment_operator_export)?;
+        self.register_function(comment_textobject_func_name, comment_textobject_export)?;
+        self.register_function(comment_append_func_name, comment_append_export)?;
+        self.register_function(comment_align_func_name, comment_align_export)?;
+        self.register_function(comment_doc_func_name, comment_doc_export)?;
+        self.register_function("todo", todo_export)?;
+        let operatorfunc = format!("v:lua.{}", self.lua_path(comment_operator_func_name));
+        self.register_function(
+            comment_operator_expr_func_name,
+            move |lua, motion: Option<String>| {
+                api::option::set(lua, "operatorfunc", Scope::Global, operatorfunc.clone())?;
+                Ok(format!("g@{}", motion.unwrap_or_default()))
+            },
+        )?;
+        highlight::enable(self.runtime)?;
         let opts = SetKeymapOpts::builder().noremap(true).silent(true).build();
+        let expr_opts = SetKeymapOpts::builder()
+            .noremap(true)
+            .silent(true)
+            .expr(true)
+            .build();
         let _ = nvim_oxi::api::set_keymap(
             Mode::Normal,
             "<C-g>",
+            format!(r#":lua {}()<CR>"#, self.lua_path(comment_line_func_name)).as_str(),
+            &opts,
+        );
+        let _ = nvim_oxi::api::set_keymap(
+            Mode::VisualSelect,
+            "<C-g>",
             format!(
-                r#":lua {}.{}.{}()<CR>"#,
-                ROOT_PLUGINS_NAME,
-                self.name(),
-                comment_line_func_name
+                r#":lua {}()<CR>"#,
+                self.lua_path(comment_multiline_func_name)
             )
             .as_str(),
             &opts,
         );
         let _ = nvim_oxi::api::set_keymap(
-            Mode::VisualSelect,
-            "<C-g>",
+            Mode::Visual,
+            "gc",
+            format!(
+                r#":lua {}()<CR>"#,
+                self.lua_path(comment_multiline_func_name)
+            )
+            .as_str(),
+            &opts,
+        );
+        let _ = nvim_oxi::api::set_keymap(
+            Mode::Visual,
+            "gb",
+            format!(r#":lua {}()<CR>"#, self.lua_path(comment_block_func_name)).as_str(),
+            &opts,
+        );
+        // gc{motion} 与 gcc 通过 operatorfunc 执行，支持计数与 `.` 重复
+        let _ = nvim_oxi::api::set_keymap(
+            Mode::Normal,
+            "gc",
+            format!("v:lua.{}()", self.lua_path(comment_operator_expr_func_name)).as_str(),
+            &expr_opts,
+        );
+        let _ = nvim_oxi::api::set_keymap(
+            Mode::Normal,
+            "gcc",
             format!(
-                r#":lua {}.{}.{}()<CR>"#,
-                ROOT_PLUGINS_NAME,
-                self.name(),
-                comment_multiline_func_name
+                "v:lua.{}('_')",
+                self.lua_path(comment_operator_expr_func_name)
             )
             .as_str(),
+            &expr_opts,
+        );
+        let _ = nvim_oxi::api::set_keymap(
+            Mode::Normal,
+            "gcA",
+            format!(r#":lua {}()<CR>"#, self.lua_path(comment_append_func_name)).as_str(),
+            &opts,
+        );
+        let _ = nvim_oxi::api::set_keymap(
+            Mode::Visual,
+            "gA",
+            format!(r#":lua {}()<CR>"#, self.lua_path(comment_align_func_name)).as_str(),
+            &opts,
+        );
+        let _ = nvim_oxi::api::set_keymap(
+            Mode::Normal,
+            "gcd",
+            format!(r#":lua {}()<CR>"#, self.lua_path(comment_doc_func_name)).as_str(),
             &opts,
         );
+        for mode in [Mode::Visual, Mode::OperatorPending] {
+            for (lhs, around) in [("ic", false), ("ac", true)] {
+                let _ = nvim_oxi::api::set_keymap(
+                    mode,
+                    lhs,
+                    format!(
+                        r#":<C-u>lua {}({})<CR>"#,
+                        self.lua_path(comment_textobject_func_name),
+                        around
+                    )
+                    .as_str(),
+                    &opts,
+                );
+            }
+        }
         Ok(())
     }
 
@@ -69,22 +176,40 @@ impl<'lua> Plugin<'lua> for Comment<'lua> {
     }
 }
 
+impl Comment<'_> {
+    /// lua 中的函数路径，例如 `plugins.comment.comment_operator`
+    fn lua_path(&self, func_name: &str) -> String {
+        format!("{}.{}.{}", ROOT_PLUGINS_NAME, self.name(), func_name)
+    }
+}
+
 struct VisualSelection {
     pub start_row: usize,
-    #[allow(unused)]
     pub start_col: usize,
     pub end_row: usize,
-    #[allow(unused)]
     pub end_col: usize,
 }
 
 /// comment one line toggle call by nvim
 fn comment_line_toggle_export(lua: &Lua, (): ()) -> LuaResult<()> {
     if let Ok(current_line) = nvim_oxi::api::get_current_line() {
-        let filetype: String = api::buffer::filetype(lua)?;
-        if let Some(comment_string) = config::comment_string(filetype) {
-            let output = comment_line_toggle(comment_string.as_str(), current_line)?;
-            let _ = nvim_oxi::api::set_current_line(output);
+        let row = api::func::getpos(lua, ".")?.row - 1;
+        match config_at(lua, Point::new(row, indent::indent_len(&current_line)))? {
+            Some(CommentConfig {
+                line: Some(comment_string),
+                ..
+            }) => {
+                let output = comment_line_toggle(comment_string.as_str(), current_line.clone())?;
+                api::text_edit::apply(lua, 0, vec![line_edit(row, &current_line, &output)])?;
+            }
+            Some(CommentConfig {
+                block: Some((open, close)),
+                ..
+            }) => {
+                let edits = block::block_lines_toggle(&[current_line], row, &open, &close);
+                api::text_edit::apply(lua, 0, edits)?;
+            }
+            _ => {}
         }
     }
     Ok(())
@@ -101,7 +226,7 @@ fn comment_line_toggle(comment_string: &str, content: String) -> LuaResult<Strin
             content.replacen(comment_string, "", 1)
         }
     } else {
-        let index = content.find(|c: char| c != ' ').unwrap_or(content.len());
+        let index = indent::indent_len(content.as_str());
         comment_line(comment_string, content, index)
     };
     Ok(output)
@@ -117,10 +242,15 @@ fn comment_line(comment_string: &str, content: String, index: usize) -> String {
     )
 }
 
+/// 行是否以注释前缀开头
+fn is_commented(comment_string: &str, content: &str) -> bool {
+    content.trim_start().starts_with(comment_string)
+}
+
 /// uncomment one line
 fn uncomment_line(comment_string: &str, content: String) -> String {
     let content_trim_start = content.trim_start();
-    if content_trim_start.starts_with(comment_string) {
+    if is_commented(comment_string, content.as_str()) {
         let pat_with_space = format!("{} ", comment_string);
         if content_trim_start.starts_with(pat_with_space.as_str()) {
             content.replacen(pat_with_space.as_str(), "", 1)
@@ -158,63 +288,493 @@ fn get_visual_selection(lua: &Lua) -> LuaResult<VisualSelection> {
 }
 
 fn comment_multiline_toggle_export(lua: &Lua, (): ()) -> LuaResult<()> {
-    let filetype: String = api::buffer::filetype(lua)?;
-    if let Some(comment_string) = config::comment_string(filetype) {
-        let selection = get_visual_selection(lua)?;
-        let start_row = selection.start_row - 1;
-        let end_row = selection.end_row;
-        let lines = api::buffer::get_lines(lua, 0, start_row, end_row, false)?;
-        let output_lines = comment_multiline_toggle(comment_string.as_str(), lines);
-        let cache_output_lines = lua.create_table()?;
-        for (index, value) in output_lines {
-            cache_output_lines.set(index, value)?;
+    let selection = get_visual_selection(lua)?;
+    toggle_lines(lua, selection.start_row - 1, selection.end_row)
+}
+
+/// operatorfunc，切换 '[ 到 '] 之间的整行注释
+fn comment_operator_export(lua: &Lua, _motion: String) -> LuaResult<()> {
+    let start = api::func::getpos(lua, "'[")?;
+    let end = api::func::getpos(lua, "']")?;
+    let (start_row, end_row) = if start.row <= end.row {
+        (start.row, end.row)
+    } else {
+        (end.row, start.row)
+    };
+    toggle_lines(lua, start_row.saturating_sub(1), end_row)
+}
+
+/// 切换 [start_row, end_row) 行的注释
+fn toggle_lines(lua: &Lua, start_row: usize, end_row: usize) -> LuaResult<()> {
+    let lines = get_lines(lua, start_row, end_row)?;
+    let point = Point::new(
+        start_row,
+        lines.first().map_or(0, |x| indent::indent_len(x)),
+    );
+    let Some(config) = config_at(lua, point)? else {
+        return Ok(());
+    };
+    let edits = match config {
+        CommentConfig {
+            line: Some(comment_string),
+            ..
+        } => {
+            let opts = IndentOpts::current(lua)?;
+            let output_lines = comment_multiline_toggle(comment_string.as_str(), &lines, opts);
+            lines
+                .iter()
+                .zip(output_lines)
+                .enumerate()
+                .map(|(offset, (old, new))| line_edit(start_row + offset, old, &new))
+                .collect()
+        }
+        CommentConfig {
+            block: Some((open, close)),
+            ..
+        } => block::block_lines_toggle(&lines, start_row, &open, &close),
+        _ => Vec::new(),
+    };
+    api::text_edit::apply(lua, 0, edits)
+}
+
+/// 注释文本对象，选中光标所在的连续注释行
+/// around 为 true 时整行选中，否则只选中注释文本
+fn comment_textobject_export(lua: &Lua, around: bool) -> LuaResult<()> {
+    let row = api::func::getpos(lua, ".")?.row - 1;
+    let line_count: usize = api::builtin_fn::call_api(lua, "nvim_buf_line_count", (0,))?;
+    let lines = get_lines(lua, 0, line_count)?;
+    let Some(line) = lines.get(row) else {
+        return Ok(());
+    };
+    let Some(CommentConfig {
+        line: Some(comment_string),
+        ..
+    }) = config_at(lua, Point::new(row, indent::indent_len(line)))?
+    else {
+        return Ok(());
+    };
+    let Some(rows) = textobject::comment_rows(&lines, row, comment_string.as_str()) else {
+        return Ok(());
+    };
+    if around {
+        return select(lua, "V", Point::new(rows.0, 0), Point::new(rows.1 - 1, 0));
+    }
+    let range = textobject::inner_range(&lines, rows, comment_string.as_str());
+    if range.is_empty() {
+        return Ok(());
+    }
+    // 可视选区包含结束位置的字符
+    let end = Point::new(range.end.row, range.end.col - 1);
+    select(lua, "v", range.start, end)
+}
+
+/// 以 mode 进入可视模式并选中 start 到 end
+fn select(lua: &Lua, mode: &str, start: Point, end: Point) -> LuaResult<()> {
+    let set_cursor = |point: Point| -> LuaResult<()> {
+        api::builtin_fn::call_api(lua, "nvim_win_set_cursor", (0, (point.row + 1, point.col)))
+    };
+    set_cursor(start)?;
+    Command::new("normal").bang().arg(mode).exec(lua)?;
+    set_cursor(end)
+}
+
+/// 在当前行末尾追加注释并进入插入模式
+fn comment_append_export(lua: &Lua, (): ()) -> LuaResult<()> {
+    let Ok(current_line) = nvim_oxi::api::get_current_line() else {
+        return Ok(());
+    };
+    let row = api::func::getpos(lua, ".")?.row - 1;
+    let point = Point::new(row, indent::indent_len(&current_line));
+    let (open, close) = match config_at(lua, point)? {
+        Some(CommentConfig {
+            line: Some(comment_string),
+            ..
+        }) => (comment_string, None),
+        Some(CommentConfig {
+            block: Some((open, close)),
+            ..
+        }) => (open, Some(close)),
+        _ => return Ok(()),
+    };
+    let code_end = trailing::append_start(&current_line);
+    let (text, cursor) = trailing::append_text(&current_line, &open, close.as_deref());
+    let range = Range::new(
+        Point::new(row, code_end),
+        Point::new(row, current_line.len()),
+    );
+    api::text_edit::apply(lua, 0, vec![TextEdit::new(range, text.as_str())])?;
+    api::builtin_fn::call_api::<_, ()>(
+        lua,
+        "nvim_win_set_cursor",
+        (0, (row + 1, code_end + cursor)),
+    )?;
+    // 块注释时在光标处插入，否则在行尾插入
+    match close {
+        Some(_) => Command::new("startinsert").exec(lua),
+        None => Command::new("startinsert").bang().exec(lua),
+    }
+}
+
+/// 将可视选区内的行尾注释对齐到同一列
+fn comment_align_export(lua: &Lua, (): ()) -> LuaResult<()> {
+    let selection = get_visual_selection(lua)?;
+    let start_row = selection.start_row - 1;
+    let lines = get_lines(lua, start_row, selection.end_row)?;
+    let point = Point::new(
+        start_row,
+        lines.first().map_or(0, |x| indent::indent_len(x)),
+    );
+    let leader = match config_at(lua, point)? {
+        Some(CommentConfig {
+            line: Some(comment_string),
+            ..
+        }) => comment_string,
+        Some(CommentConfig {
+            block: Some((open, _)),
+            ..
+        }) => open,
+        _ => return Ok(()),
+    };
+    let output_lines = trailing::align(&lines, leader.as_str(), IndentOpts::current(lua)?);
+    let edits = lines
+        .iter()
+        .zip(output_lines)
+        .enumerate()
+        .map(|(offset, (old, new))| line_edit(start_row + offset, old, &new))
+        .collect();
+    api::text_edit::apply(lua, 0, edits)
+}
+
+/// 在光标所在的函数/类型上方插入文档注释模板并进入插入模式
+fn comment_doc_export(lua: &Lua, (): ()) -> LuaResult<()> {
+    let position = api::func::getpos(lua, ".")?;
+    let point = Point::new(position.row - 1, position.col.saturating_sub(1));
+    let Some((item, language)) = api::treesitter::ancestors(lua, 0, point, false)?
+        .into_iter()
+        .find_map(|node| {
+            doc::language(node.lang.as_str())
+                .filter(|language| language.items.contains(&node.kind.as_str()))
+                .map(|language| (node, language))
+        })
+    else {
+        return Ok(());
+    };
+    // 注入语言使用其文件类型的配置
+    let filetype = api::buffer::filetype(lua)?;
+    let filetypes = api::treesitter::filetypes(lua, item.lang.as_str())?;
+    let filetype = if filetypes.contains(&filetype) {
+        filetype
+    } else {
+        filetypes.into_iter().next().unwrap_or(item.lang.clone())
+    };
+    let Some(style) = config::doc_style(lua, filetype.as_str()) else {
+        return Ok(());
+    };
+    let info = doc::collect(lua, &item, &language)?;
+    let template = doc::render(style, &info);
+    let item_row = item.range.start.row;
+    let lines = get_lines(lua, 0, item_row + 1)?;
+    let row = doc::insert_row(&lines, item_row, language.attribute);
+    let indent = &lines[row][..indent::indent_len(&lines[row])];
+    let text = template
+        .lines
+        .iter()
+        .map(|line| format!("{indent}{line}\n"))
+        .collect::<String>();
+    api::text_edit::apply(
+        lua,
+        0,
+        vec![TextEdit::insert(Point::new(row, 0), text.as_str())],
+    )?;
+    let cursor_line = &template.lines[template.cursor_row];
+    api::builtin_fn::call_api::<_, ()>(
+        lua,
+        "nvim_win_set_cursor",
+        (
+            0,
+            (
+                row + template.cursor_row + 1,
+                indent.len() + cursor_line.len(),
+            ),
+        ),
+    )?;
+    Command::new("startinsert").bang().exec(lua)
+}
+
+/// 扫描当前目录下注释中的 TODO / FIXME 等标记，输出到 quickfix 或选择器
+fn todo_export(lua: &Lua, opts: LuaValue) -> LuaResult<()> {
+    let opts: TodoOpts = match opts {
+        LuaValue::Nil => TodoOpts::default(),
+        opts => lua.from_value(opts)?,
+    };
+    let root = PathBuf::from(api::builtin_fn::getcwd(lua)?);
+    let walk_root = root.clone();
+    // 遍历与读取文件在工作线程中执行，注释配置需要在主线程中查询
+    api::job::background(
+        lua,
+        move || todo::walk(&walk_root),
+        move |lua, paths| {
+            let files = todo_configs(lua, paths)?;
+            let tags = opts.tags();
+            let scan_tags = tags.clone();
+            api::job::background(
+                lua,
+                move || todo::scan(files, &scan_tags),
+                move |lua, items| todo_output(lua, &root, &opts, &tags, &items),
+            )
+        },
+    )
+}
+
+/// 文件的注释配置，跳过没有配置的文件
+fn todo_configs(lua: &Lua, paths: Vec<PathBuf>) -> LuaResult<Vec<(PathBuf, CommentConfig)>> {
+    // 按文件类型缓存注释配置，文件类型由文件名匹配，不能按后缀缓存
+    let mut configs = HashMap::<String, Option<CommentConfig>>::new();
+    let mut files = Vec::new();
+    for path in paths {
+        let Some(filetype) = api::buffer::filetype_match(lua, &path.to_string_lossy())? else {
+            continue;
+        };
+        let config = match configs.get(&filetype) {
+            Some(config) => config.clone(),
+            None => {
+                let commentstring: String =
+                    api::option::filetype_option(lua, filetype.as_str(), "commentstring")?;
+                let config = config::resolve(lua, filetype.as_str(), commentstring.as_str());
+                configs.insert(filetype, config.clone());
+                config
+            }
+        };
+        if let Some(config) = config {
+            files.push((path, config));
+        }
+    }
+    Ok(files)
+}
+
+/// 扫描结果输出到 quickfix 或选择器
+/// 路径相对于扫描时的 root，quickfix 中使用绝对路径
+fn todo_output(
+    lua: &Lua,
+    root: &Path,
+    opts: &TodoOpts,
+    tags: &[String],
+    items: &[TodoItem],
+) -> LuaResult<()> {
+    let relative = |path: &Path| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    };
+    let groups = todo::group_by_file(items);
+    if opts.picker {
+        let files = groups
+            .iter()
+            .map(|group| format!("{} ({})", relative(&group[0].path), todo::summary(group)))
+            .collect();
+        let groups = groups.into_iter().map(<[_]>::to_vec).collect::<Vec<_>>();
+        let select_opts = SelectOpts {
+            prompt: Some(format!("{} ({})", tags.join("/"), items.len())),
+            kind: Some("comment_todo".to_string()),
+        };
+        return api::ui::select(lua, files, select_opts.clone(), move |lua, choice| {
+            let Some((index, _)) = choice else {
+                return Ok(());
+            };
+            let group = groups[index].clone();
+            let entries = group
+                .iter()
+                .map(|item| format!("{}: {}", item.row + 1, item.tagged))
+                .collect();
+            api::ui::select(lua, entries, select_opts, move |lua, choice| {
+                let Some((index, _)) = choice else {
+                    return Ok(());
+                };
+                let item = &group[index];
+                Command::new("edit")
+                    .arg(&item.path.to_string_lossy())
+                    .exec(lua)?;
+                api::builtin_fn::call_api::<_, ()>(
+                    lua,
+                    "nvim_win_set_cursor",
+                    (0, (item.row + 1, item.tagged.col)),
+                )
+            })
+        });
+    }
+    // 每个文件之前插入一条无效项显示该文件的统计
+    let mut quickfix_items = Vec::new();
+    for group in &groups {
+        quickfix_items.push(QuickfixItem {
+            text: format!("{}: {}", relative(&group[0].path), todo::summary(group)),
+            valid: false,
+            ..Default::default()
+        });
+        quickfix_items.extend(group.iter().map(|item| {
+            QuickfixItem::new(
+                &item.path.to_string_lossy(),
+                item.row + 1,
+                item.tagged.col + 1,
+                &item.tagged.to_string(),
+            )
+        }));
+    }
+    let title = format!(
+        "{}: {} in {} files",
+        tags.join("/"),
+        items.len(),
+        groups.len()
+    );
+    api::quickfix::create(lua, ListKind::Quickfix, &title, quickfix_items, None)?;
+    api::quickfix::open(lua, ListKind::Quickfix, None)
+}
+
+/// 用块注释包裹可视选区，字符选择时只包裹选中的部分
+fn comment_block_toggle_export(lua: &Lua, (): ()) -> LuaResult<()> {
+    let selection = get_visual_selection(lua)?;
+    let mode: String = api::builtin_fn::call_fn(lua, "visualmode", ())?;
+    let range = selection_range(lua, &selection, mode == "V")?;
+    let Some((open, close)) = config_at(lua, range.start)?.and_then(|x| x.block) else {
+        return Ok(());
+    };
+    let text = api::buffer::get_text(lua, 0, range)?.join("\n");
+    let edits = block::block_toggle(text.as_str(), range, &open, &close);
+    api::text_edit::apply(lua, 0, edits)
+}
+
+/// 可视选区对应的范围，'> 的列指向最后一个字符的首字节
+fn selection_range(lua: &Lua, selection: &VisualSelection, linewise: bool) -> LuaResult<Range> {
+    let end_row = selection.end_row - 1;
+    let end_line = get_lines(lua, end_row, end_row + 1)?
+        .pop()
+        .unwrap_or_default();
+    let start = if linewise {
+        Point::new(selection.start_row - 1, 0)
+    } else {
+        Point::new(
+            selection.start_row - 1,
+            selection.start_col.saturating_sub(1),
+        )
+    };
+    let end_col = if linewise {
+        end_line.len()
+    } else {
+        let col = selection.end_col.saturating_sub(1);
+        let width = end_line
+            .get(col..)
+            .and_then(|x| x.chars().next())
+            .map_or(0, char::len_utf8);
+        (col + width).min(end_line.len())
+    };
+    Ok(Range::new(start, Point::new(end_row, end_col)))
+}
+
+fn get_lines(lua: &Lua, start_row: usize, end_row: usize) -> LuaResult<Vec<String>> {
+    api::buffer::get_lines(lua, 0, start_row, end_row, false)?
+        .sequence_values()
+        .collect()
+}
+
+/// point 处语言的注释配置
+/// treesitter 注入的语言使用其文件类型的 'commentstring'，没有配置时依次使用外层语言，最后使用当前 buffer 的文件类型
+fn config_at(lua: &Lua, point: Point) -> LuaResult<Option<CommentConfig>> {
+    let filetype = api::buffer::filetype(lua)?;
+    let mut candidates = Vec::new();
+    for lang in api::treesitter::languages_at(lua, 0, point)? {
+        let filetypes = api::treesitter::filetypes(lua, lang.as_str())?;
+        if filetypes.contains(&filetype) {
+            break;
         }
-        api::buffer::set_lines(lua, 0, start_row, end_row, false, cache_output_lines)?;
+        let injected = filetypes.into_iter().next().unwrap_or(lang);
+        let commentstring: String =
+            api::option::filetype_option(lua, injected.as_str(), "commentstring")?;
+        candidates.push((injected, commentstring));
     }
+    let commentstring: String = api::option::get(lua, "commentstring", Scope::Buffer(0))?;
+    candidates.push((filetype, commentstring));
+    Ok(config::resolve_first(lua, &candidates))
+}
+
+/// 插件配置，例如 `plugins.comment.setup({ filetypes = { html = { block = { "<!--", "-->" } } } })`
+fn setup(lua: &Lua, opts: LuaValue) -> LuaResult<()> {
+    let opts: SetupOpts = lua.from_value(opts)?;
+    config::setup(lua, opts);
     Ok(())
 }
 
+/// 整行替换，由 text_edit 裁剪为最小修改，保留行内 mark
+fn line_edit(row: usize, old: &str, new: &str) -> TextEdit {
+    TextEdit::new(
+        Range::new(Point::new(row, 0), Point::new(row, old.len())),
+        new,
+    )
+}
+
 /// comment toggle multiline
-fn comment_multiline_toggle(comment_string: &str, lines: LuaTable) -> Vec<(usize, String)> {
-    let mut output_lines = Vec::new();
+/// 空白行不参与判断且保持不变，注释前缀插入到公共缩进的显示列
+fn comment_multiline_toggle(
+    comment_string: &str,
+    lines: &[String],
+    opts: IndentOpts,
+) -> Vec<String> {
     // check comment or uncomment
-    let pairs: LuaTablePairs<usize, String> = lines.pairs();
-    let (comment_flag, list, comment_index) = pairs.fold(
-        (false, Vec::new(), usize::MAX),
-        |(mut comment_flag, mut list, mut comment_index), pair| {
-            if let Ok((index, value)) = pair {
-                if !value.trim_start().starts_with(comment_string) && value != "" {
-                    comment_flag = true;
-                }
-                let line_index = value.find(|c: char| c != ' ').unwrap_or(value.len());
-                if line_index < comment_index {
-                    comment_index = line_index;
-                }
-                list.push((index, value));
-            }
-            (comment_flag, list, comment_index)
-        },
-    );
+    let comment_flag = lines
+        .iter()
+        .any(|value| !is_commented(comment_string, value) && !indent::is_blank_line(value));
+    let comment_col = lines
+        .iter()
+        .filter(|value| !indent::is_blank_line(value))
+        .map(|value| indent::indent_width(value, opts))
+        .min()
+        .unwrap_or(0);
     if comment_flag {
         // comment multiline
-        for (index, value) in list {
-            if value == "" {
-                output_lines.push((index, value));
-            } else {
-                output_lines.push((index, comment_line(comment_string, value, comment_index)));
-            }
-        }
+        let leader = format!("{comment_string} ");
+        lines
+            .iter()
+            .map(|value| {
+                if indent::is_blank_line(value) {
+                    value.clone()
+                } else {
+                    indent::insert_at_column(value, comment_col, leader.as_str(), opts)
+                }
+            })
+            .collect()
     } else {
         // uncomment multiline
-        for (index, value) in list {
-            output_lines.push((index, uncomment_line(comment_string, value)))
-        }
+        lines
+            .iter()
+            .map(|value| uncomment_line(comment_string, value.clone()))
+            .collect()
     }
-    output_lines
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
-    fn comment_line_toggle_works() {}
+    fn comment_line_toggle_works() {
+        let output = comment_line_toggle("//", "    let a = 1;".to_string()).unwrap();
+        assert_eq!(output, "    // let a = 1;");
+        let output = comment_line_toggle("//", output).unwrap();
+        assert_eq!(output, "    let a = 1;");
+    }
+
+    #[test]
+    fn comment_multiline_toggle_works() {
+        let opts = IndentOpts::default();
+        let lines = vec!["  a".to_string(), "    b".to_string()];
+        let output = comment_multiline_toggle("--", &lines, opts);
+        assert_eq!(output, vec!["  -- a", "  --   b"]);
+        assert_eq!(comment_multiline_toggle("--", &output, opts), lines);
+        let lines = vec![
+            "\tif a {".to_string(),
+            "  ".to_string(),
+            "\t\tb".to_string(),
+        ];
+        let output = comment_multiline_toggle("//", &lines, opts);
+        assert_eq!(output, vec!["\t// if a {", "  ", "\t// \tb"]);
+    }
 }
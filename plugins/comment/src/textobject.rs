@@ -0,0 +1,50 @@
+use api::range::{Point, Range};
+
+use crate::is_commented;
+
+/// 包含 row 的连续注释行 [start_row, end_row)，row 不是注释行时返回 None
+pub fn comment_rows(lines: &[String], row: usize, comment_string: &str) -> Option<(usize, usize)> {
+    let commented = |row: &usize| is_commented(comment_string, lines[*row].as_str());
+    if row >= lines.len() || !commented(&row) {
+        return None;
+    }
+    let start_row = (0..row).rev().take_while(commented).last().unwrap_or(row);
+    let end_row = (row + 1..lines.len())
+        .take_while(commented)
+        .last()
+        .map_or(row + 1, |row| row + 1);
+    Some((start_row, end_row))
+}
+
+/// 注释文本的范围，不含注释前缀及其后的一个空格，不含行尾空白
+pub fn inner_range(
+    lines: &[String],
+    (start_row, end_row): (usize, usize),
+    comment_string: &str,
+) -> Range {
+    let first = lines[start_row].as_str();
+    let leader_end = first.len() - first.trim_start().len() + comment_string.len();
+    let start_col = leader_end + usize::from(first[leader_end..].starts_with(' '));
+    let last = lines[end_row - 1].trim_end();
+    Range::new(
+        Point::new(start_row, start_col),
+        Point::new(end_row - 1, last.len()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comment_rows_works() {
+        let lines = ["fn a() {}", "// a", "  //b", "// c  ", "let b;"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(comment_rows(&lines, 2, "//"), Some((1, 4)));
+        assert_eq!(comment_rows(&lines, 0, "//"), None);
+        let range = inner_range(&lines, (1, 4), "//");
+        assert_eq!(range, Range::new(Point::new(1, 3), Point::new(3, 4)));
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+use serde::Deserialize;
+
+use crate::config::CommentConfig;
+
+/// 默认扫描的标记
+pub const DEFAULT_TAGS: &[&str] = &["TODO", "FIXME", "HACK", "NOTE"];
+
+/// todo 参数，例如 `plugins.comment.todo({ tags = { "FIXME" }, picker = true })`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct TodoOpts {
+    /// 只扫描这些标记，为空时使用 DEFAULT_TAGS
+    pub tags: Vec<String>,
+    /// 使用 vim.ui.select 选择，否则输出到 quickfix
+    pub picker: bool,
+}
+
+impl TodoOpts {
+    pub fn tags(&self) -> Vec<String> {
+        if self.tags.is_empty() {
+            DEFAULT_TAGS.iter().map(|x| x.to_string()).collect()
+        } else {
+            self.tags.clone()
+        }
+    }
+}
+
+/// 注释中的标记，例如 `// TODO(name, 2024-01-02): text`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tagged {
+    pub tag: String,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub text: String,
+    /// 标记所在的字节列
+    pub col: usize,
+}
+
+impl Display for Tagged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.tag, self.text)?;
+        let meta = self
+            .author
+            .iter()
+            .chain(&self.date)
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !meta.is_empty() {
+            write!(f, " ({})", meta.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// 扫描结果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TodoItem {
+    pub path: PathBuf,
+    pub row: usize,
+    pub tagged: Tagged,
+}
+
+/// 文本中的一个标记
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagMatch<'a> {
+    /// 标记在 tags 中的下标
+    pub index: usize,
+    pub start: usize,
+    /// 包含紧随其后的 `(...)` 与 `:`
+    pub end: usize,
+    /// 括号中的内容，例如 `name, 2024-01-02`
+    pub meta: Option<&'a str>,
+}
+
+/// 文本中作为完整单词出现的标记
+/// 标记之后为 `:`、`(...)`、空白或行尾，例如 `TODO(name):`、`SAFETY:`
+pub fn find_tags<'a, S: AsRef<str>>(text: &'a str, tags: &[S]) -> Vec<TagMatch<'a>> {
+    let mut found = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        match match_at(text, start, tags) {
+            Some(tag) => {
+                start = tag.end;
+                found.push(tag);
+            }
+            None => start += text[start..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    found
+}
+
+fn match_at<'a, S: AsRef<str>>(text: &'a str, start: usize, tags: &[S]) -> Option<TagMatch<'a>> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    if text[..start].ends_with(is_word) {
+        return None;
+    }
+    tags.iter().enumerate().find_map(|(index, tag)| {
+        let tag = tag.as_ref();
+        if !text[start..].starts_with(tag) {
+            return None;
+        }
+        let mut end = start + tag.len();
+        let meta = text[end..]
+            .strip_prefix('(')
+            .and_then(|x| x.split_once(')'))
+            .map(|(meta, _)| meta);
+        if let Some(meta) = meta {
+            end += meta.len() + 2;
+        }
+        let rest = &text[end..];
+        if !(rest.is_empty() || rest.starts_with(':') || rest.starts_with(char::is_whitespace)) {
+            return None;
+        }
+        if rest.starts_with(':') {
+            end += 1;
+        }
+        Some(TagMatch {
+            index,
+            start,
+            end,
+            meta,
+        })
+    })
+}
+
+/// 解析一行中位于注释内的第一个标记
+pub fn parse_line(line: &str, config: &CommentConfig, tags: &[String]) -> Option<Tagged> {
+    let comment_start = config.leader_end(line)?;
+    let comment = &line[comment_start..];
+    let tag = find_tags(comment, tags).into_iter().next()?;
+    let mut text = comment[tag.end..].trim();
+    if let Some((_, close)) = &config.block {
+        text = text.strip_suffix(close.as_str()).unwrap_or(text).trim_end();
+    }
+    let (mut author, mut date) = (None, None);
+    for part in tag
+        .meta
+        .into_iter()
+        .flat_map(|x| x.split(','))
+        .map(str::trim)
+    {
+        if is_date(part) {
+            date = Some(part.to_string());
+        } else if !part.is_empty() {
+            author = Some(part.to_string());
+        }
+    }
+    Some(Tagged {
+        tag: tags[tag.index].clone(),
+        author,
+        date,
+        text: text.to_string(),
+        col: comment_start + tag.start,
+    })
+}
+
+/// 形如 `2024-01-02` / `2024/01/02` 的日期
+fn is_date(text: &str) -> bool {
+    text.len() >= 8
+        && text.starts_with(|c: char| c.is_ascii_digit())
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == '/')
+}
+
+/// root 下的文件，遵循 `.gitignore`，按路径排序
+pub fn walk(root: &Path) -> Vec<PathBuf> {
+    WalkBuilder::new(root)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|x| x.is_file()))
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// 按注释配置扫描文件中的标记
+pub fn scan(files: Vec<(PathBuf, CommentConfig)>, tags: &[String]) -> Vec<TodoItem> {
+    let mut items = Vec::new();
+    for (path, config) in files {
+        // 跳过无法读取或非 utf-8 的文件
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        items.extend(content.lines().enumerate().filter_map(|(row, line)| {
+            parse_line(line, &config, tags).map(|tagged| TodoItem {
+                path: path.clone(),
+                row,
+                tagged,
+            })
+        }));
+    }
+    items
+}
+
+/// 按文件分组，scan 的结果已按路径排序
+pub fn group_by_file(items: &[TodoItem]) -> Vec<&[TodoItem]> {
+    items.chunk_by(|a, b| a.path == b.path).collect()
+}
+
+/// 每种标记的数量，例如 `FIXME 1, TODO 2`
+pub fn summary(items: &[TodoItem]) -> String {
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for item in items {
+        *counts.entry(item.tagged.tag.as_str()).or_default() += 1;
+    }
+    counts
+        .iter()
+        .map(|(tag, count)| format!("{tag} {count}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_line_works() {
+        let tags = TodoOpts::default().tags();
+        let config = CommentConfig {
+            line: Some("//".to_string()),
+            block: Some(("/*".to_string(), "*/".to_string())),
+        };
+        let tagged = parse_line("let a = 1; // TODO(bob, 2024-01-02): fix", &config, &tags);
+        assert_eq!(
+            tagged,
+            Some(Tagged {
+                tag: "TODO".to_string(),
+                author: Some("bob".to_string()),
+                date: Some("2024-01-02".to_string()),
+                text: "fix".to_string(),
+                col: 14,
+            })
+        );
+        assert_eq!(tagged.unwrap().to_string(), "[TODO] fix (bob, 2024-01-02)");
+        let tagged = parse_line("/* FIXME later */", &config, &tags).unwrap();
+        assert_eq!(
+            (tagged.tag.as_str(), tagged.text.as_str()),
+            ("FIXME", "later")
+        );
+        assert_eq!(parse_line("let TODO = 1;", &config, &tags), None);
+        assert_eq!(parse_line("// TODOS: a", &config, &tags), None);
+        assert_eq!(parse_line("// MYTODO: a", &config, &tags), None);
+    }
+
+    #[test]
+    fn find_tags_works() {
+        let tags = ["TODO", "SAFETY"];
+        let found = find_tags("// TODO(bob): a TODOS SAFETY: b", &tags)
+            .into_iter()
+            .map(|x| (x.start, x.end, x.index))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![(3, 13, 0), (22, 29, 1)]);
+        assert_eq!(find_tags("let MY_TODO = 1;", &tags), vec![]);
+        let tags = ["WARN", "WARNING"];
+        assert_eq!(find_tags("WARNING: a", &tags)[0].index, 1);
+    }
+}
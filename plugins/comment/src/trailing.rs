@@ -0,0 +1,96 @@
+use crate::indent::{self, IndentOpts};
+
+/// 行尾注释前缀的字节位置，前缀之前需要有代码且以空白分隔
+pub fn trailing_comment(line: &str, leader: &str) -> Option<usize> {
+    line.match_indices(leader)
+        .map(|(index, _)| index)
+        .find(|&index| {
+            let code = &line[..index];
+            !code.trim().is_empty() && code.ends_with(indent::is_blank)
+        })
+}
+
+/// 追加注释的起始位置，替换行尾空白，空白行保留缩进
+pub fn append_start(line: &str) -> usize {
+    if indent::is_blank_line(line) {
+        indent::indent_len(line)
+    } else {
+        line.trim_end().len()
+    }
+}
+
+/// 在行尾追加注释时插入的文本及插入后光标相对插入位置的偏移
+/// 块注释时光标位于注释符号之间
+pub fn append_text(line: &str, open: &str, close: Option<&str>) -> (String, usize) {
+    let separator = if line.trim().is_empty() { "" } else { " " };
+    let prefix = format!("{separator}{open} ");
+    let cursor = prefix.len();
+    match close {
+        Some(close) => (format!("{prefix} {close}"), cursor),
+        None => (prefix, cursor),
+    }
+}
+
+/// 对齐行尾注释到公共列，公共列为最长代码的显示宽度加一个空格
+/// 没有行尾注释的行保持不变
+pub fn align(lines: &[String], leader: &str, opts: IndentOpts) -> Vec<String> {
+    let comments = lines
+        .iter()
+        .map(|line| trailing_comment(line, leader))
+        .collect::<Vec<_>>();
+    let column = lines
+        .iter()
+        .zip(&comments)
+        .filter_map(|(line, index)| index.map(|index| line[..index].trim_end()))
+        .map(|code| indent::display_width(code, opts))
+        .max()
+        .unwrap_or(0)
+        + 1;
+    lines
+        .iter()
+        .zip(comments)
+        .map(|(line, index)| match index {
+            Some(index) => {
+                let code = line[..index].trim_end();
+                let padding = column - indent::display_width(code, opts);
+                format!("{}{}{}", code, " ".repeat(padding), &line[index..])
+            }
+            None => line.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_works() {
+        let lines = [
+            "const A: u8 = 1; // a",
+            "const LONG: u8 = 2;   // b",
+            "// c",
+            "let url = \"http://x\";",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+        let output = align(&lines, "//", IndentOpts::default());
+        assert_eq!(
+            output,
+            vec![
+                "const A: u8 = 1;    // a",
+                "const LONG: u8 = 2; // b",
+                "// c",
+                "let url = \"http://x\";",
+            ]
+        );
+        assert_eq!(
+            append_text("a", "/*", Some("*/")),
+            (" /*  */".to_string(), 4)
+        );
+        assert_eq!(append_text("", "#", None), ("# ".to_string(), 2));
+        assert_eq!(append_start("    "), 4);
+        assert_eq!(append_start("\ta;  "), 3);
+    }
+}
@@ -41,7 +41,7 @@ impl<'lua> Project<'lua> {
         let root_path = PathBuf::from(path);
         let mut root_node = FileNode::try_from_path(root_path.clone())?;
         if root_node.is_dir() {
-            Self::load_dir(lua, &mut root_node, root_path)?;
+            Self::load_dir(&mut root_node, root_path)?;
         }
         root_table.set("root", lua.to_value(&root_node)?)?;
         Ok(root_table)
@@ -63,9 +63,8 @@ impl<'lua> Project<'lua> {
         }
     }
 
-    fn load_dir(lua: &Lua, parent_node: &mut FileNode, parent_path: PathBuf) -> LuaResult<()> {
+    fn load_dir(parent_node: &mut FileNode, parent_path: PathBuf) -> LuaResult<()> {
         let mut buffer = fs::read_dir(parent_path)?
-            .into_iter()
             .filter_map(|x| x.ok())
             .collect::<Vec<_>>();
         buffer.sort_by(Self::file_sorter);
@@ -73,7 +72,7 @@ impl<'lua> Project<'lua> {
             let child_path = child_entry.path();
             let mut child_node = FileNode::try_from_path(child_path.clone())?;
             if child_node.is_dir() {
-                Self::load_dir(lua, &mut child_node, child_path)?;
+                Self::load_dir(&mut child_node, child_path)?;
             }
             parent_node.add_child(child_node);
         }
@@ -128,7 +128,6 @@ impl FileTree {
     /// 非递归加载目录内容
     fn load_directory(root_path: &str, root_node: Rc<RefCell<FileNode>>) -> std::io::Result<()> {
         let mut buffer = fs::read_dir(root_path)?
-            .into_iter()
             .filter_map(|x| x.ok())
             .map(|x| (root_node.clone(), x))
             .collect::<Vec<_>>();
@@ -160,7 +159,6 @@ impl FileTree {
 
     fn load(lua: &Lua, parent_table: &LuaTable, parent_path: PathBuf) -> LuaResult<()> {
         let mut buffer = fs::read_dir(parent_path)?
-            .into_iter()
             .filter_map(|x| x.ok())
             .collect::<Vec<_>>();
         while !buffer.is_empty() {
@@ -1,3 +1,4 @@
+use api::command::Command;
 use mlua::prelude::{LuaResult, LuaTable};
 use mlua::Error::RuntimeError;
 use mlua::{Lua, LuaSerdeExt};
@@ -102,8 +103,8 @@ impl<'lua> Session<'lua> {
 
     fn make_session(lua: &Lua, (): ()) -> LuaResult<()> {
         let cwd = api::builtin_fn::getcwd(lua)?;
-        let cmd = if let Ok(session) = Self::query_session(lua, cwd.as_str()) {
-            format!("mks! {}", session.data)
+        let file_path = if let Ok(session) = Self::query_session(lua, cwd.as_str()) {
+            session.data
         } else {
             let session_path = SessionPath::try_new(lua)?;
             let mut file_name = api::util::generate_random_string(8);
@@ -113,16 +114,17 @@ impl<'lua> Session<'lua> {
                 file_name = api::util::generate_random_string(8);
                 file_path = format!("{}/{}.vim", session_path.plugin, file_name);
             }
-            let cmd = format!("mks! {}", file_path);
             let data = SessionData {
                 path: cwd,
-                data: file_path,
+                data: file_path.clone(),
             };
             Self::save_session(lua, data)?;
-            cmd
+            file_path
         };
-        api::cmd(lua, cmd)?;
-        Ok(())
+        Command::new("mksession")
+            .bang()
+            .arg(file_path.as_str())
+            .exec(lua)
     }
 
     fn save_session(lua: &Lua, session: SessionData) -> LuaResult<()> {
@@ -174,15 +176,9 @@ impl<'lua> Session<'lua> {
         // 数据库删除文件系统文件不存在的记录
         let fs_not_exists = list
             .iter()
-            .filter_map(|session| {
-                if let Ok(true) = fs::exists(session.data.as_str()) {
-                    None
-                } else {
-                    Some(session)
-                }
-            })
+            .filter(|session| !matches!(fs::exists(session.data.as_str()), Ok(true)))
             .collect::<Vec<_>>();
-        if fs_not_exists.len() > 0 {
+        if !fs_not_exists.is_empty() {
             let clean_sql = format!(
                 r#"
               DELETE FROM {PLUGIN_NAME}
@@ -209,13 +205,19 @@ impl<'lua> Session<'lua> {
                 }
             })
             .collect::<HashSet<_>>();
-        walk_dir_vim(session_path.plugin.as_str())
+        let orphan_files = walk_dir_vim(session_path.plugin.as_str())
             .into_iter()
-            .for_each(|session_file| {
-                if !fs_exists.contains(&session_file) {
-                    let _ = fs::remove_file(session_file.as_str());
-                }
+            .filter(|session_file| !fs_exists.contains(session_file))
+            .collect::<Vec<_>>();
+        if orphan_files.is_empty() {
+            return Ok(());
+        }
+        let message = format!("Delete {} unused session files?", orphan_files.len());
+        if api::ui::confirm(lua, message.as_str(), &["&Yes", "&No"], 1)? == Some(0) {
+            orphan_files.iter().for_each(|session_file| {
+                let _ = fs::remove_file(session_file.as_str());
             });
+        }
         Ok(())
     }
 }
@@ -260,12 +262,8 @@ pub fn walk_dir_vim(dir_path: &str) -> Vec<String> {
             .filter_map(|entry| {
                 let mut res = None;
                 let path = entry.path();
-                if path.is_file() {
-                    if let Some(ext) = path.extension() {
-                        if ext == "vim" {
-                            res = Some(format!("{}", path.display()));
-                        }
-                    }
+                if path.is_file() && path.extension().is_some_and(|ext| ext == "vim") {
+                    res = Some(format!("{}", path.display()));
                 }
                 res
             })